@@ -0,0 +1,127 @@
+//! Compress and decompress throughput on a few kinds of input.
+//!
+//! Run with `cargo bench`. Each case is repeated for about a second and the
+//! best run is reported, in MB/s of uncompressed data.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lzss::{Lzss, MatchStrategy};
+
+const SIZE: usize = 4 << 20;
+
+/// Smaller, as without a limit on the search it compresses at under 1 MB/s
+const ADVERSARIAL_SIZE: usize = 1 << 20;
+
+/// xorshift64, so every run benchmarks the same bytes
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// English-like text built from a small vocabulary
+fn text(rng: &mut Rng) -> Vec<u8> {
+    const WORDS: &[&str] = &[
+        "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog", "ring", "buffer",
+        "window", "match", "length", "position", "tree", "node", "compress", "data", "and", "of",
+    ];
+    let mut data = Vec::with_capacity(SIZE);
+    while data.len() < SIZE {
+        data.extend_from_slice(WORDS[(rng.next() % WORDS.len() as u64) as usize].as_bytes());
+        data.push(if rng.next().is_multiple_of(12) { b'\n' } else { b' ' });
+    }
+    data.truncate(SIZE);
+    data
+}
+
+/// Uniformly random bytes, which don't compress at all
+fn random(rng: &mut Rng) -> Vec<u8> {
+    (0..SIZE).map(|_| rng.next() as u8).collect()
+}
+
+/// Little-endian records with small counters, runs of zeros and noise
+fn mixed(rng: &mut Rng) -> Vec<u8> {
+    let mut data = Vec::with_capacity(SIZE);
+    let mut counter = 0u32;
+    while data.len() < SIZE {
+        counter += (rng.next() % 4) as u32;
+        data.extend_from_slice(&counter.to_le_bytes());
+        data.extend_from_slice(&[0; 6][..(rng.next() % 7) as usize]);
+        data.extend_from_slice(&rng.next().to_le_bytes()[..(rng.next() % 5) as usize]);
+    }
+    data.truncate(SIZE);
+    data
+}
+
+/// Runs of zeros broken by a single 1 every 1000 bytes. With a long
+/// lookahead, the strings in a run differ only at the 1, so the tree for 0
+/// grows deep and every comparison along it is long.
+fn adversarial() -> Vec<u8> {
+    (0..ADVERSARIAL_SIZE).map(|i| (i % 1000 == 999) as u8).collect()
+}
+
+/// Runs `f` for about a second and returns the best MB/s over `bytes`
+fn measure(bytes: usize, mut f: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    let mut best = Duration::MAX;
+    while start.elapsed() < Duration::from_secs(1) {
+        let run = Instant::now();
+        f();
+        best = best.min(run.elapsed());
+    }
+    bytes as f64 / best.as_secs_f64() / 1e6
+}
+
+fn main() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let inputs = [
+        ("text", text(&mut rng)),
+        ("random", random(&mut rng)),
+        ("mixed", mixed(&mut rng)),
+        // A single byte value, the deepest possible trees if full matches
+        // didn't replace their node
+        ("same", vec![0xAA; SIZE]),
+    ];
+
+    for strategy in [MatchStrategy::BinaryTree, MatchStrategy::HashChain] {
+        println!("{:?}", strategy);
+        let mut lzss = Lzss::new().with_match_strategy(strategy);
+        for (name, data) in &inputs {
+            let compressed = lzss.compress(data).unwrap();
+            let ratio = compressed.len() as f64 / data.len() as f64 * 100.0;
+
+            let compress = measure(data.len(), || {
+                black_box(lzss.compress(black_box(data)).unwrap());
+            });
+            let decompress = measure(data.len(), || {
+                black_box(lzss.decompress(black_box(&compressed)).unwrap());
+            });
+
+            println!(
+                "  {:<8} {:>5.1}% of original  compress {:>7.1} MB/s  decompress {:>7.1} MB/s",
+                name, ratio, compress, decompress
+            );
+        }
+    }
+    println!("BinaryTree, window 16, lookahead 255, adversarial input");
+    let data = adversarial();
+    for max_chain in [None, Some(64), Some(16)] {
+        let lzss = Lzss::with_params(16, 255).unwrap();
+        let mut lzss = match max_chain {
+            Some(max_chain) => lzss.with_max_chain(max_chain),
+            None => lzss,
+        };
+        let ratio = lzss.compress(&data).unwrap().len() as f64 / data.len() as f64 * 100.0;
+        let compress = measure(data.len(), || {
+            black_box(lzss.compress(black_box(&data)).unwrap());
+        });
+        let label = max_chain.map_or("no limit".to_string(), |max_chain| format!("max_chain {}", max_chain));
+        println!("  {:<12} {:>5.1}% of original  compress {:>7.2} MB/s", label, ratio, compress);
+    }
+}
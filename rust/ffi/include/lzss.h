@@ -0,0 +1,56 @@
+/*
+ * C interface to the Rust LZSS compressor, in the format of Okumura's
+ * LZSS.C: a 4096-byte window, matches of 3 to 18 bytes, and the ring
+ * buffer cleared to spaces. Link with liblzss_ffi, built by
+ * `cargo build --release` in rust/ffi.
+ *
+ * Kept by hand in step with src/lib.rs.
+ */
+#ifndef LZSS_H
+#define LZSS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The call succeeded. */
+#define LZSS_OK 0
+/* A pointer was null where data was expected. */
+#define LZSS_ERROR_NULL (-1)
+/* The output didn't fit in the buffer given for it. */
+#define LZSS_ERROR_BUFFER_TOO_SMALL (-2)
+/* The compressed input is truncated or refers to data before its start. */
+#define LZSS_ERROR_CORRUPT (-3)
+
+/*
+ * Compresses src_len bytes at src into the buffer of dst_cap bytes at dst,
+ * storing the compressed length in *out_len. Returns LZSS_OK, or
+ * LZSS_ERROR_BUFFER_TOO_SMALL if the output doesn't fit;
+ * lzss_compress_bound() gives a size that always does. src and dst may be
+ * NULL if their length is 0.
+ */
+int32_t lzss_compress(const uint8_t *src, size_t src_len,
+                      uint8_t *dst, size_t dst_cap, size_t *out_len);
+
+/*
+ * Reverse of lzss_compress(), also returning LZSS_ERROR_CORRUPT if src
+ * isn't a valid compressed stream. The decompressed length isn't stored
+ * in the stream; dst_cap has to be enough for it, as with LZSS.C.
+ */
+int32_t lzss_decompress(const uint8_t *src, size_t src_len,
+                        uint8_t *dst, size_t dst_cap, size_t *out_len);
+
+/*
+ * The largest compressed size of src_len bytes: a flags byte for every
+ * eight units, and each byte sent as one.
+ */
+size_t lzss_compress_bound(size_t src_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LZSS_H */
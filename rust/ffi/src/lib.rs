@@ -0,0 +1,94 @@
+//! C interface to the LZSS compressor, in the format of Okumura's LZSS.C,
+//! so programs built around that can link this in its place. See
+//! `include/lzss.h` for the declarations.
+
+use core::slice;
+
+use lzss::{Lzss, LzssError};
+
+/// The call succeeded.
+pub const LZSS_OK: i32 = 0;
+/// A pointer was null where data was expected.
+pub const LZSS_ERROR_NULL: i32 = -1;
+/// The output didn't fit in the buffer given for it.
+pub const LZSS_ERROR_BUFFER_TOO_SMALL: i32 = -2;
+/// The compressed input is truncated or refers to data before its start.
+pub const LZSS_ERROR_CORRUPT: i32 = -3;
+
+/// The status code for `result`, storing the length it carries in `out_len`.
+fn status(result: Result<usize, LzssError>, out_len: *mut usize) -> i32 {
+    match result {
+        Ok(len) => {
+            // SAFETY: the callers checked that out_len isn't null, and the
+            // caller of the C function that it points to a size_t.
+            unsafe { *out_len = len };
+            LZSS_OK
+        }
+        Err(LzssError::BufferTooSmall) => LZSS_ERROR_BUFFER_TOO_SMALL,
+        Err(_) => LZSS_ERROR_CORRUPT,
+    }
+}
+
+/// The input and output buffers as slices, or None if a pointer that must
+/// not be null is. Null is fine for an empty buffer.
+///
+/// # Safety
+///
+/// As for lzss_compress().
+unsafe fn buffers<'a>(src: *const u8, src_len: usize, dst: *mut u8, dst_cap: usize) -> Option<(&'a [u8], &'a mut [u8])> {
+    if (src.is_null() && src_len > 0) || (dst.is_null() && dst_cap > 0) {
+        return None;
+    }
+    // SAFETY: the pointers are not null unless the lengths are 0, and the
+    // caller promises they are valid for those lengths.
+    unsafe {
+        let src = if src_len == 0 { &[][..] } else { slice::from_raw_parts(src, src_len) };
+        let dst = if dst_cap == 0 { &mut [][..] } else { slice::from_raw_parts_mut(dst, dst_cap) };
+        Some((src, dst))
+    }
+}
+
+/// Compresses `src_len` bytes at `src` into the buffer of `dst_cap` bytes
+/// at `dst`, storing the compressed length in `*out_len`. Returns
+/// `LZSS_OK`, or `LZSS_ERROR_BUFFER_TOO_SMALL` if the output doesn't fit;
+/// lzss_compress_bound() gives a size that always does.
+///
+/// # Safety
+///
+/// `src` must be valid for reading `src_len` bytes and `dst` for writing
+/// `dst_cap` bytes, and the two must not overlap. `out_len` must point to
+/// a writable `size_t`. Null pointers are caught and give
+/// `LZSS_ERROR_NULL`, except that `src` and `dst` may be null if their
+/// length is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzss_compress(src: *const u8, src_len: usize, dst: *mut u8, dst_cap: usize, out_len: *mut usize) -> i32 {
+    // SAFETY: passed on from the caller
+    let Some((src, dst)) = (unsafe { buffers(src, src_len, dst, dst_cap) }).filter(|_| !out_len.is_null()) else {
+        return LZSS_ERROR_NULL;
+    };
+    status(Lzss::lzss_c().compress_into(src, dst), out_len)
+}
+
+/// Reverse of lzss_compress(), also returning `LZSS_ERROR_CORRUPT` if
+/// `src` isn't a valid compressed stream. The decompressed length isn't
+/// stored in the stream; `dst_cap` has to be enough for it, as with
+/// LZSS.C.
+///
+/// # Safety
+///
+/// As for lzss_compress().
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzss_decompress(src: *const u8, src_len: usize, dst: *mut u8, dst_cap: usize, out_len: *mut usize) -> i32 {
+    // SAFETY: passed on from the caller
+    let Some((src, dst)) = (unsafe { buffers(src, src_len, dst, dst_cap) }).filter(|_| !out_len.is_null()) else {
+        return LZSS_ERROR_NULL;
+    };
+    status(Lzss::lzss_c().decompress_into(src, dst), out_len)
+}
+
+/// The largest compressed size of `src_len` bytes: a flags byte for every
+/// eight units, and each byte sent as one.
+#[unsafe(no_mangle)]
+pub extern "C" fn lzss_compress_bound(src_len: usize) -> usize {
+    src_len.saturating_add(src_len.div_ceil(8))
+}
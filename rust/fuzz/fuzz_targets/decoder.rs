@@ -0,0 +1,25 @@
+#![no_main]
+
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use lzss::{Lzss, LzssDecoder};
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the parameters and the second how the rest is
+    // split up, so other pair layouts and every split point get exercised.
+    let [params, chunk, input @ ..] = data else { return };
+
+    let window_bits = 8 + (params & 7) as u32;
+    let lookahead = 2 + (params >> 3) as usize;
+    let Ok(lzss) = Lzss::with_params(window_bits, lookahead) else { return };
+    let Ok(lzss) = lzss.with_threshold(1 + (params >> 6) as usize) else { return };
+
+    let mut decoder = LzssDecoder::with_lzss(Vec::new(), lzss);
+    for piece in input.chunks(1 + *chunk as usize) {
+        if decoder.write_all(piece).is_err() {
+            return;
+        }
+    }
+    let _ = decoder.finish();
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lzss::Lzss;
+
+fuzz_target!(|data: &[u8]| {
+    // Any input may be rejected, but none may panic.
+    let _ = Lzss::new().decompress(data);
+});
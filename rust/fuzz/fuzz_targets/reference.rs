@@ -0,0 +1,73 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lzss::Lzss;
+
+/// Decodes `input` the obvious way, to check Lzss::decompress() against:
+/// no ring buffer, just the output so far behind the F zero bytes the
+/// stream may refer back into, with each pair's distance worked out from
+/// where the ring buffer would be. None for anything malformed.
+fn reference_decode(input: &[u8], window_bits: u32, lookahead: usize, threshold: usize) -> Option<Vec<u8>> {
+    let n = 1usize << window_bits;
+    let pair_len = if window_bits <= 15 { 2 } else { 3 };
+    let length_bits = 8 * pair_len as u32 - window_bits;
+
+    let mut history = vec![0u8; lookahead];
+    let mut rest = input;
+    while let Some((&flags, units)) = rest.split_first() {
+        if units.is_empty() {
+            return None;
+        }
+        rest = units;
+        for bit in 0..8 {
+            if rest.is_empty() {
+                break;
+            }
+            if flags & (1 << bit) != 0 {
+                history.push(rest[0]);
+                rest = &rest[1..];
+                continue;
+            }
+            let (pair, tail) = rest.split_at_checked(pair_len)?;
+            rest = tail;
+            let last = pair[pair_len - 1] as usize;
+            let low = pair[..pair_len - 1].iter().rev().fold(0, |i, &b| i << 8 | b as usize);
+            let pos = low | (last >> length_bits) << (8 * (pair_len - 1));
+            let length = (last & ((1 << length_bits) - 1)) + threshold + 1;
+
+            // Decoding starts at N - F in the ring buffer
+            let written = history.len() - lookahead;
+            let r = (n - lookahead + written) % n;
+            let distance = match (r + n - pos) % n {
+                0 => n,
+                d => d,
+            };
+            if distance > history.len().min(n) {
+                return None;
+            }
+            for _ in 0..length {
+                history.push(history[history.len() - distance]);
+            }
+        }
+    }
+    Some(history.split_off(lookahead))
+}
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the parameters, as in the decoder target
+    let [params, input @ ..] = data else { return };
+
+    let window_bits = 8 + (params & 7) as u32;
+    let lookahead = 2 + (params >> 3) as usize;
+    let threshold = 1 + (params >> 6) as usize;
+    let Ok(lzss) = Lzss::with_params(window_bits, lookahead) else { return };
+    let Ok(mut lzss) = lzss.with_threshold(threshold) else { return };
+
+    // Arbitrary bytes: both accept the same streams and agree on them
+    let expected = reference_decode(input, window_bits, lookahead, threshold);
+    assert_eq!(lzss.decompress(input).ok(), expected);
+
+    // A valid stream of the same bytes, with plenty of pairs in it
+    let compressed = lzss.compress(input).unwrap();
+    assert_eq!(reference_decode(&compressed, window_bits, lookahead, threshold).as_deref(), Some(input));
+});
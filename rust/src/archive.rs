@@ -0,0 +1,151 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::error::LzssError;
+use crate::framed::check_name;
+use crate::lzss_stream::Lzss;
+
+/// First bytes of every archive
+const MAGIC: [u8; 4] = *b"LZSA";
+
+/// Version written by compress_archive(), and the newest one read
+const VERSION: u8 = 1;
+
+/// Length of the fixed header: the magic, the version and the number of
+/// entries as u32 LE
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4;
+
+/// Length of an entry in the table of contents, apart from its path: the
+/// path's length as u16 LE, then after the path the mode as u32 LE and the
+/// data's length before and after compression as u64 LE
+const ENTRY_LEN: usize = 2 + 4 + 8 + 8;
+
+/// A file in an archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Where the file goes, relative to the directory the archive is
+    /// extracted into, with `/` between components. Paths that could lead
+    /// outside that directory, being absolute or having a `..`, empty or
+    /// `.` component, a `\`, or a `:` as in a drive letter, are rejected
+    /// when writing and reading.
+    pub path: String,
+    /// Unix permission bits of the file
+    pub mode: u32,
+    /// Contents of the file
+    pub data: Vec<u8>,
+}
+
+/// Accepts only a relative path of ordinary components, which can't lead
+/// outside the directory it is joined to on any platform.
+pub(crate) fn check_path(path: &str) -> Result<(), LzssError> {
+    if path.contains(':') {
+        return Err(LzssError::InvalidFileName);
+    }
+    path.split('/').try_for_each(check_name)
+}
+
+/// Takes `len` bytes off the front of `rest`.
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], LzssError> {
+    let (taken, tail) = rest.split_at_checked(len).ok_or(LzssError::TruncatedStream)?;
+    *rest = tail;
+    Ok(taken)
+}
+
+impl Lzss {
+    /// Packs `entries` into one archive: a table of contents giving each
+    /// one's path, mode and lengths, followed by each one's data as
+    /// compress_framed() writes it, so every file carries its own
+    /// parameters and incompressible ones are stored. The magic is
+    /// `b"LZSA"`, so is_framed() tells archives apart.
+    ///
+    /// ```
+    /// use lzss::{ArchiveEntry, Lzss, LzssError};
+    ///
+    /// let entries = vec![
+    ///     ArchiveEntry { path: "README".into(), mode: 0o644, data: b"read me, read me".to_vec() },
+    ///     ArchiveEntry { path: "src/main.c".into(), mode: 0o600, data: b"int main() { return 0; }".to_vec() },
+    /// ];
+    /// let mut lzss = Lzss::new();
+    /// let archive = lzss.compress_archive(&entries)?;
+    /// assert_eq!(lzss.decompress_archive(&archive)?, entries);
+    ///
+    /// let escape = ArchiveEntry { path: "../etc/passwd".into(), ..Default::default() };
+    /// assert!(matches!(lzss.compress_archive(&[escape]), Err(LzssError::InvalidFileName)));
+    /// # Ok::<(), LzssError>(())
+    /// ```
+    pub fn compress_archive(&mut self, entries: &[ArchiveEntry]) -> Result<Vec<u8>, LzssError> {
+        let count = u32::try_from(entries.len()).map_err(|_| LzssError::InvalidParams)?;
+        let bodies = entries
+            .iter()
+            .map(|entry| self.compress_framed(&entry.data))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut output = Vec::new();
+        output.extend_from_slice(&MAGIC);
+        output.push(VERSION);
+        output.extend_from_slice(&count.to_le_bytes());
+        for (entry, body) in entries.iter().zip(&bodies) {
+            check_path(&entry.path)?;
+            let path_len = u16::try_from(entry.path.len()).map_err(|_| LzssError::InvalidFileName)?;
+            output.extend_from_slice(&path_len.to_le_bytes());
+            output.extend_from_slice(entry.path.as_bytes());
+            output.extend_from_slice(&entry.mode.to_le_bytes());
+            output.extend_from_slice(&(entry.data.len() as u64).to_le_bytes());
+            output.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        }
+        for body in &bodies {
+            output.extend_from_slice(body);
+        }
+        Ok(output)
+    }
+
+    /// Reverse of compress_archive(). Fails with `LzssError::BadMagic` if
+    /// `buffer` isn't an archive, with `LzssError::InvalidFileName` if any
+    /// path could lead outside the directory it is extracted into, and
+    /// with `LzssError::LengthMismatch` if a file's data doesn't have the
+    /// length the table of contents gives.
+    pub fn decompress_archive(&mut self, buffer: &[u8]) -> Result<Vec<ArchiveEntry>, LzssError> {
+        if !Self::is_archive(buffer) {
+            return Err(LzssError::BadMagic);
+        }
+        let mut rest = &buffer[MAGIC.len()..];
+        let header = take(&mut rest, HEADER_LEN - MAGIC.len())?;
+        if header[0] != VERSION {
+            return Err(LzssError::UnsupportedHeader { version: header[0], flags: 0 });
+        }
+        let count = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+
+        // Every entry takes at least ENTRY_LEN bytes, so a count that
+        // doesn't fit can't make this allocate much.
+        let mut contents = Vec::with_capacity(count.min(rest.len() / ENTRY_LEN));
+        for _ in 0..count {
+            let path_len = u16::from_le_bytes(take(&mut rest, 2)?.try_into().unwrap()) as usize;
+            let path = core::str::from_utf8(take(&mut rest, path_len)?).map_err(|_| LzssError::InvalidFileName)?;
+            check_path(path)?;
+            let fields = take(&mut rest, ENTRY_LEN - 2)?;
+            let mode = u32::from_le_bytes(fields[..4].try_into().unwrap());
+            let len = u64::from_le_bytes(fields[4..12].try_into().unwrap());
+            let body_len = u64::from_le_bytes(fields[12..].try_into().unwrap());
+            contents.push((path, mode, len, body_len));
+        }
+
+        let mut entries = Vec::with_capacity(contents.len());
+        for (path, mode, len, body_len) in contents {
+            let body_len = usize::try_from(body_len).map_err(|_| LzssError::TruncatedStream)?;
+            let data = self.decompress_framed(take(&mut rest, body_len)?)?;
+            if data.len() as u64 != len {
+                return Err(LzssError::LengthMismatch { expected: len });
+            }
+            entries.push(ArchiveEntry { path: String::from(path), mode, data });
+        }
+        if !rest.is_empty() {
+            return Err(LzssError::InvalidBlockIndex);
+        }
+        Ok(entries)
+    }
+
+    /// Whether `buffer` starts with the magic of an archive, as written by
+    /// compress_archive().
+    pub fn is_archive(buffer: &[u8]) -> bool {
+        buffer.starts_with(&MAGIC)
+    }
+}
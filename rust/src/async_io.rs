@@ -0,0 +1,201 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::lzss_stream::Lzss;
+
+/// Wraps an async reader of uncompressed data and reads back its compressed
+/// form, like LzssEncoder. Encoding itself is synchronous; a read is only
+/// pending while the inner reader is.
+///
+/// ```
+/// use lzss::AsyncLzssEncoder;
+/// use tokio::io::AsyncReadExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let file: &[u8] = b"hello hello hello";
+/// let mut compressed = Vec::new();
+/// AsyncLzssEncoder::new(file).read_to_end(&mut compressed).await?;
+/// assert_eq!(compressed, lzss::compress(file)?);
+/// # Ok::<(), lzss::LzssError>(())
+/// # })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct AsyncLzssEncoder<R: AsyncRead + Unpin> {
+    inner: R,
+    lzss: Lzss,
+    /// compressed bytes not yet handed out by poll_read()
+    buf: Vec<u8>,
+    pos: usize,
+    /// whether inner has hit EOF and the final code block is in buf
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncLzssEncoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the encoding.
+    pub fn with_lzss(inner: R, mut lzss: Lzss) -> Self {
+        lzss.encode_begin();
+        Self {
+            inner,
+            lzss,
+            buf: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncLzssEncoder<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let mut chunk = [0u8; 8192];
+
+        // Encoding a chunk may not complete a code block, so keep going until
+        // there is something to return or nothing left to encode.
+        while this.pos == this.buf.len() && !this.finished {
+            this.buf.clear();
+            this.pos = 0;
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            match chunk.filled() {
+                [] => {
+                    this.lzss.encode_finish(&mut this.buf)?;
+                    this.finished = true;
+                }
+                data => this.lzss.encode_update(data, &mut this.buf)?,
+            }
+        }
+
+        let n = out.remaining().min(this.buf.len() - this.pos);
+        out.put_slice(&this.buf[this.pos..this.pos + n]);
+        this.pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Accepts compressed bytes through `AsyncWrite` and writes the
+/// decompressed data to an inner async writer, like LzssDecoder.
+///
+/// Shut it down once all input is written, to detect a truncated stream,
+/// which fails with `InvalidData`, and to shut the inner writer down.
+pub struct AsyncLzssDecoder<W: AsyncWrite + Unpin> {
+    inner: W,
+    lzss: Lzss,
+    /// decompressed bytes not yet written to inner
+    buf: Vec<u8>,
+    pos: usize,
+    /// whether the end of the compressed data was checked
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncLzssDecoder<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the decoding.
+    pub fn with_lzss(inner: W, mut lzss: Lzss) -> Self {
+        lzss.decode_begin();
+        Self {
+            inner,
+            lzss,
+            buf: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns the inner writer. Decompressed bytes not yet written to it
+    /// are lost unless the decoder was flushed or shut down first.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes out the decompressed bytes held back so far.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pos < self.buf.len() {
+            match ready!(Pin::new(&mut self.inner).poll_write(cx, &self.buf[self.pos..]))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                n => self.pos += n,
+            }
+        }
+        self.buf.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncLzssDecoder<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, input: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // Input is only taken once the output of the last write is out, so
+        // a slow writer holds back the compressed data too.
+        ready!(this.poll_drain(cx))?;
+        this.lzss.decode_update(input, &mut this.buf)?;
+        Poll::Ready(Ok(input.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.finished {
+            this.lzss.decode_finish()?;
+            this.finished = true;
+        }
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn round_trips_through_a_duplex() {
+        let data = b"she sells sea shells by the sea shore, the shells she sells ".repeat(2000);
+
+        // A small duplex buffer makes both sides wait on each other.
+        let (mut near, mut far) = tokio::io::duplex(64);
+        let source = data.clone();
+        let writer = tokio::spawn(async move {
+            let mut encoder = AsyncLzssEncoder::new(&source[..]);
+            tokio::io::copy(&mut encoder, &mut near).await?;
+            near.shutdown().await
+        });
+
+        let mut decoder = AsyncLzssDecoder::new(Vec::new());
+        tokio::io::copy(&mut far, &mut decoder).await.unwrap();
+        decoder.shutdown().await.unwrap();
+        writer.await.unwrap().unwrap();
+        assert_eq!(decoder.into_inner(), data);
+    }
+
+    #[tokio::test]
+    async fn truncated_input_fails_at_shutdown() {
+        let data = b"abracadabra, abracadabra".repeat(10);
+        let mut compressed = Vec::new();
+        AsyncLzssEncoder::new(&data[..]).read_to_end(&mut compressed).await.unwrap();
+
+        let mut decoder = AsyncLzssDecoder::new(Vec::new());
+        decoder.write_all(&compressed[..compressed.len() - 1]).await.unwrap();
+        let e = decoder.shutdown().await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
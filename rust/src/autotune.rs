@@ -0,0 +1,88 @@
+use crate::lzss_stream::Lzss;
+
+/// Window bits tried by autotune(), smallest first
+const WINDOW_BITS: [u32; 6] = [10, 12, 14, 16, 18, 20];
+
+/// Lookahead tried by autotune() besides the longest each window allows:
+/// that of new()
+const CLASSIC_LOOKAHEAD: usize = Lzss::F;
+
+/// Window and lookahead for with_params(), as chosen by autotune().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LzssParams {
+    /// The ring buffer holds `1 << window_bits` bytes.
+    pub window_bits: u32,
+    /// Upper limit for the length of a match
+    pub lookahead: usize,
+}
+
+impl Default for LzssParams {
+    /// The parameters of new()
+    fn default() -> Self {
+        Self { window_bits: Lzss::WINDOW_BITS, lookahead: Lzss::F }
+    }
+}
+
+impl Lzss {
+    /// Compresses `sample` with a small grid of windows, from 1 KiB to 1
+    /// MiB, and for each the lookahead of new(), where it fits, and the
+    /// longest one the window allows, and returns whichever gave the smallest output. Ties go to
+    /// the smaller window, which takes less memory and time, and then to
+    /// the shorter lookahead.
+    ///
+    /// The sample should be typical of the data to come and large enough
+    /// to show its repeats; larger windows only pay off when the sample
+    /// reaches back that far. Each of the nine candidates compresses it in
+    /// full, so a few hundred KB is plenty. An empty sample gives the
+    /// parameters of new().
+    ///
+    /// The choice only binds the decompressor if it travels with the
+    /// stream, as in compress_framed():
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// // 40 KB of xorshift noise, then the same 40 KB twice more
+    /// let mut x = 1u32;
+    /// let noise: Vec<u8> = (0..40_000)
+    ///     .map(|_| {
+    ///         x ^= x << 13;
+    ///         x ^= x >> 17;
+    ///         x ^= x << 5;
+    ///         (x >> 24) as u8
+    ///     })
+    ///     .collect();
+    /// let repetitive = noise.repeat(3);
+    ///
+    /// let params = Lzss::autotune(&repetitive);
+    /// assert!(params.window_bits > Lzss::autotune(&noise).window_bits);
+    ///
+    /// let framed = Lzss::with_params(params.window_bits, params.lookahead)?.compress_framed(&repetitive)?;
+    /// assert!(framed.len() < repetitive.len() / 2);
+    /// assert_eq!(Lzss::new().decompress_framed(&framed)?, repetitive);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn autotune(sample: &[u8]) -> LzssParams {
+        let mut best = (u64::MAX, LzssParams::default());
+        if sample.is_empty() {
+            return best.1;
+        }
+
+        for window_bits in WINDOW_BITS {
+            let longest = Self::longest_lookahead(window_bits);
+            let lookaheads = [CLASSIC_LOOKAHEAD, longest];
+            for lookahead in lookaheads.into_iter().filter(|&lookahead| lookahead <= longest) {
+                let mut lzss = Self::with_params(window_bits, lookahead).expect("lookahead fits the window");
+                let Ok(size) = lzss.compressed_size(sample) else {
+                    continue;
+                };
+                if size < best.0 {
+                    best = (size, LzssParams { window_bits, lookahead });
+                }
+            }
+        }
+
+        best.1
+    }
+}
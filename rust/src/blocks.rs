@@ -0,0 +1,210 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+
+/// Length of the fixed part of the block index: the uncompressed length as
+/// an 8-byte and the block size as a 4-byte little-endian integer
+const INDEX_HEADER_LEN: usize = 12;
+
+/// Each block's compressed length, as a 4-byte little-endian integer
+const INDEX_ENTRY_LEN: usize = 4;
+
+/// Block stream taken apart by parse_index()
+struct Index<'a> {
+    len: u64,
+    block_size: usize,
+    blocks: Vec<&'a [u8]>,
+}
+
+impl Index<'_> {
+    /// Number of bytes block i should decompress to
+    fn block_len(&self, i: usize) -> usize {
+        let start = i as u64 * self.block_size as u64;
+        (self.len - start).min(self.block_size as u64) as usize
+    }
+}
+
+/// Prefixes the compressed blocks with their index.
+fn assemble(len: usize, block_size: usize, compressed: Vec<Vec<u8>>) -> Result<Vec<u8>, LzssError> {
+    let index_len = INDEX_HEADER_LEN + compressed.len() * INDEX_ENTRY_LEN;
+    let mut output = Vec::with_capacity(index_len + compressed.iter().map(Vec::len).sum::<usize>());
+    output.extend_from_slice(&(len as u64).to_le_bytes());
+    output.extend_from_slice(&(block_size as u32).to_le_bytes());
+    for block in &compressed {
+        let block_len = u32::try_from(block.len()).map_err(|_| LzssError::InvalidParams)?;
+        output.extend_from_slice(&block_len.to_le_bytes());
+    }
+    for block in &compressed {
+        output.extend_from_slice(block);
+    }
+    Ok(output)
+}
+
+/// Splits a block stream into its blocks, checking that the index accounts
+/// for exactly the bytes that follow it.
+fn parse_index(input: &[u8]) -> Result<Index<'_>, LzssError> {
+    if input.len() < INDEX_HEADER_LEN {
+        return Err(LzssError::TruncatedStream);
+    }
+    let len = u64::from_le_bytes(input[..8].try_into().unwrap());
+    let block_size = u32::from_le_bytes(input[8..INDEX_HEADER_LEN].try_into().unwrap()) as usize;
+    if block_size == 0 && len > 0 {
+        return Err(LzssError::InvalidBlockIndex);
+    }
+
+    let count = if len == 0 { 0 } else { len.div_ceil(block_size as u64) };
+    let rest = &input[INDEX_HEADER_LEN..];
+    let index_len = count.checked_mul(INDEX_ENTRY_LEN as u64).ok_or(LzssError::TruncatedStream)?;
+    if index_len > rest.len() as u64 {
+        return Err(LzssError::TruncatedStream);
+    }
+    let (mut entries, mut data) = rest.split_at(index_len as usize);
+
+    let mut blocks = Vec::with_capacity(count as usize);
+    while let Some((entry, tail)) = entries.split_first_chunk::<INDEX_ENTRY_LEN>() {
+        let block_len = u32::from_le_bytes(*entry) as usize;
+        if block_len > data.len() {
+            return Err(LzssError::TruncatedStream);
+        }
+        let (block, tail_data) = data.split_at(block_len);
+        blocks.push(block);
+        data = tail_data;
+        entries = tail;
+    }
+    if !data.is_empty() {
+        return Err(LzssError::InvalidBlockIndex);
+    }
+
+    Ok(Index { len, block_size, blocks })
+}
+
+/// Joins decompressed blocks, checking each has the length the index implies.
+fn concat(index: &Index<'_>, decompressed: Vec<Vec<u8>>) -> Result<Vec<u8>, LzssError> {
+    let mut output = Vec::with_capacity(decompressed.iter().map(Vec::len).sum());
+    for (i, block) in decompressed.iter().enumerate() {
+        if block.len() != index.block_len(i) {
+            return Err(LzssError::InvalidBlockIndex);
+        }
+        output.extend_from_slice(block);
+    }
+    Ok(output)
+}
+
+impl Lzss {
+    /// Compresses `buffer` as independent blocks of `block_size` bytes
+    /// (the last may be shorter), each a complete stream like compress()
+    /// produces, behind an index of their compressed lengths.
+    ///
+    /// Independent blocks compress a little worse, since no match reaches
+    /// into an earlier block, but they can be compressed and decompressed
+    /// in parallel. The output only depends on `block_size`, not on how
+    /// many threads produced it. `block_size` must be between 1 and
+    /// `u32::MAX`, otherwise `LzssError::InvalidParams` is returned.
+    pub fn compress_blocks(&mut self, buffer: &[u8], block_size: usize) -> Result<Vec<u8>, LzssError> {
+        if block_size == 0 || block_size > u32::MAX as usize {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let compressed = buffer
+            .chunks(block_size)
+            .map(|block| self.compress(block))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assemble(buffer.len(), block_size, compressed)
+    }
+
+    /// Reverse of compress_blocks() and compress_parallel(). Fails with
+    /// `LzssError::InvalidBlockIndex` if the index doesn't match the blocks.
+    pub fn decompress_blocks(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let index = parse_index(buffer)?;
+
+        let decompressed = index
+            .blocks
+            .iter()
+            .map(|block| self.decompress(block))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        concat(&index, decompressed)
+    }
+
+    /// Like compress_blocks(), with the blocks spread over all available
+    /// cores, or as many threads as with_threads() allows. The output is byte-for-byte the same as compress_blocks().
+    /// With a finder from with_match_finder(), which can't be shared
+    /// between threads, this falls back to compress_blocks().
+    #[cfg(feature = "parallel")]
+    pub fn compress_parallel(&mut self, buffer: &[u8], block_size: usize) -> Result<Vec<u8>, LzssError> {
+        if block_size == 0 || block_size > u32::MAX as usize {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let blocks: Vec<&[u8]> = buffer.chunks(block_size).collect();
+        let compressed = match self.run_parallel(&blocks, |lzss, _, block| lzss.compress(block)) {
+            Some(compressed) => compressed?,
+            None => return self.compress_blocks(buffer, block_size),
+        };
+
+        assemble(buffer.len(), block_size, compressed)
+    }
+
+    /// Like decompress_blocks(), with the blocks spread over all available
+    /// cores, or as many threads as with_threads() allows.
+    #[cfg(feature = "parallel")]
+    pub fn decompress_parallel(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let index = parse_index(buffer)?;
+
+        let decompressed = match self.run_parallel(&index.blocks, |lzss, _, block| lzss.decompress(block)) {
+            Some(decompressed) => decompressed?,
+            None => return self.decompress_blocks(buffer),
+        };
+
+        concat(&index, decompressed)
+    }
+
+    /// Applies `op` to every block and its index on rayon's threads, each
+    /// working through a run of consecutive blocks with its own copy of
+    /// this Lzss. Results come back in block order. None if the settings
+    /// can't be copied.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn run_parallel(
+        &self,
+        blocks: &[&[u8]],
+        op: impl Fn(&mut Lzss, usize, &[u8]) -> Result<Vec<u8>, LzssError> + Sync,
+    ) -> Option<Result<Vec<Vec<u8>>, LzssError>> {
+        // The global pool has a thread per core; a limit needs a pool of
+        // its own
+        let pool = match self.threads() {
+            0 => None,
+            threads => match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => Some(pool),
+                Err(e) => return Some(Err(LzssError::Io(std::io::Error::other(e)))),
+            },
+        };
+        let threads = pool.as_ref().map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads());
+
+        let per_thread = blocks.len().div_ceil(threads).max(1);
+        let workers = blocks
+            .chunks(per_thread)
+            .enumerate()
+            .map(|(i, run)| Some((self.clone_settings()?, i * per_thread, run)))
+            .collect::<Option<Vec<_>>>()?;
+
+        let op = &op;
+        let run = move || -> Vec<Vec<_>> {
+            workers
+                .into_par_iter()
+                .map(|(mut lzss, first, run)| {
+                    run.iter().enumerate().map(|(i, block)| op(&mut lzss, first + i, block)).collect()
+                })
+                .collect()
+        };
+        let results = match pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        };
+        Some(results.into_iter().flatten().collect())
+    }
+}
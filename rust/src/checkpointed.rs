@@ -0,0 +1,177 @@
+use alloc::vec::Vec;
+
+use crate::crc32::crc32;
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+use crate::sink::{CountingSink, Sink};
+
+/// Length of the interval between checkpoints that starts the stream, as a
+/// u64 LE
+const INTERVAL_LEN: usize = 8;
+
+/// First bytes of each checkpoint
+const MARKER: [u8; 4] = *b"SYNC";
+
+/// Length of a checkpoint: the marker, the number of decompressed bytes
+/// before it as a u64 and the CRC-32 of those since the previous checkpoint
+/// as a u32, both little-endian
+const CHECKPOINT_LEN: usize = MARKER.len() + 12;
+
+/// Appends a checkpoint for `position` bytes of data, the last of which
+/// are `segment`.
+fn put_checkpoint(output: &mut Vec<u8>, position: u64, segment: &[u8]) {
+    output.extend_from_slice(&MARKER);
+    output.extend_from_slice(&position.to_le_bytes());
+    output.extend_from_slice(&crc32(segment).to_le_bytes());
+}
+
+/// Whether `checkpoint` is the one put_checkpoint() would write.
+fn checkpoint_matches(checkpoint: &[u8], position: u64, segment: &[u8]) -> bool {
+    let mut expected = Vec::with_capacity(CHECKPOINT_LEN);
+    put_checkpoint(&mut expected, position, segment);
+    checkpoint == expected
+}
+
+impl Lzss {
+    /// Compresses `buffer` like compress(), with a checkpoint after the
+    /// code block that reaches each further `interval` bytes of data, and
+    /// one at the end, all behind `interval` as a u64 LE. A checkpoint is
+    /// 16 bytes: a marker, the position in the decompressed data and a
+    /// CRC-32 of the data since the previous one. They let
+    /// verify_checkpointed() tell roughly where a damaged stream went
+    /// wrong.
+    ///
+    /// This is a diagnostic format of its own, which only
+    /// decompress_checkpointed() reads, with the `checkpointed` feature.
+    /// `interval` must not be 0.
+    pub fn compress_checkpointed(&mut self, buffer: &[u8], interval: usize) -> Result<Vec<u8>, LzssError> {
+        if interval == 0 {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let stream = self.compress(buffer)?;
+
+        // Walk what was just written a code block at a time, as the decoder
+        // will, to find where the checkpoints go.
+        let mut output = Vec::with_capacity(INTERVAL_LEN + stream.len() + (buffer.len() / interval + 1) * CHECKPOINT_LEN);
+        output.extend_from_slice(&(interval as u64).to_le_bytes());
+        let mut position = 0;
+        let mut segment_start = 0;
+        let mut next = interval;
+        let mut offset = 0;
+        self.decode_begin();
+        while offset < stream.len() {
+            if position >= next {
+                put_checkpoint(&mut output, position as u64, &buffer[segment_start..position]);
+                segment_start = position;
+                next = position + interval;
+            }
+
+            let block_len = self.code_block_len(&stream[offset..]);
+            let mut decoded = CountingSink::default();
+            self.decode_update(&stream[offset..offset + block_len], &mut decoded)?;
+            output.extend_from_slice(&stream[offset..offset + block_len]);
+            position += decoded.len() as usize;
+            offset += block_len;
+        }
+        put_checkpoint(&mut output, position as u64, &buffer[segment_start..]);
+
+        Ok(output)
+    }
+
+    /// Reverse of compress_checkpointed(). Fails with
+    /// `LzssError::CorruptedNear` at the first checkpoint that doesn't
+    /// match the data decoded up to it.
+    pub fn decompress_checkpointed(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+
+        self.walk_checkpointed(buffer, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Checks a stream made by compress_checkpointed() without keeping the
+    /// decompressed data, returning its length. If the stream is damaged,
+    /// `LzssError::CorruptedNear` gives the position of the last checkpoint
+    /// that still matched; the damage is in the `interval` or so bytes of
+    /// data after it.
+    pub fn verify_checkpointed(&mut self, buffer: &[u8]) -> Result<u64, LzssError> {
+        let mut output = CountingSink::default();
+
+        self.walk_checkpointed(buffer, &mut output)?;
+
+        Ok(output.len())
+    }
+
+    /// Decodes a checkpointed stream into `output`, checking each
+    /// checkpoint on the way.
+    fn walk_checkpointed<S: Sink>(&mut self, buffer: &[u8], output: &mut S) -> Result<(), LzssError> {
+        let (interval, rest) = buffer.split_first_chunk::<INTERVAL_LEN>().ok_or(LzssError::TruncatedStream)?;
+        let interval = u64::from_le_bytes(*interval);
+        let stream_end = rest.len().checked_sub(CHECKPOINT_LEN).ok_or(LzssError::TruncatedStream)?;
+
+        // Damage shows up as a checkpoint that doesn't match, or isn't where
+        // one is due, or as a backreference that can't be. Either way it is
+        // reported against the last checkpoint that did match.
+        let mut position: u64 = 0;
+        let mut next = interval;
+        let mut segment = Vec::new();
+        let mut offset = 0;
+        self.decode_begin();
+        while offset < stream_end {
+            let corrupted = LzssError::CorruptedNear { position };
+            let end = position + segment.len() as u64;
+            if end >= next {
+                let checkpoint = rest[..stream_end].get(offset..offset + CHECKPOINT_LEN).ok_or(corrupted)?;
+                if !checkpoint_matches(checkpoint, end, &segment) {
+                    return Err(LzssError::CorruptedNear { position });
+                }
+                output.put(&segment)?;
+                segment.clear();
+                position = end;
+                next = end.saturating_add(interval);
+                offset += CHECKPOINT_LEN;
+                continue;
+            }
+
+            let block_len = self.code_block_len(&rest[offset..stream_end]);
+            self.decode_update(&rest[offset..offset + block_len], &mut segment).map_err(|_| corrupted)?;
+            offset += block_len;
+        }
+
+        let end = position + segment.len() as u64;
+        if self.decode_finish().is_err() || !checkpoint_matches(&rest[stream_end..], end, &segment) {
+            return Err(LzssError::CorruptedNear { position });
+        }
+        output.put(&segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_is_located_within_an_interval() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 1000 / 7) as u8).collect();
+        let mut lzss = Lzss::new();
+        let checkpointed = lzss.compress_checkpointed(&data, 10_000).unwrap();
+        assert_eq!(lzss.verify_checkpointed(&checkpointed).unwrap(), 100_000);
+        assert_eq!(lzss.decompress_checkpointed(&checkpointed).unwrap(), data);
+
+        // The stream is about as compressible throughout, so the damage at
+        // a share of its length is at about that share of the data.
+        for tenth in 1..10 {
+            let mut damaged = checkpointed.clone();
+            let offset = damaged.len() * tenth / 10;
+            damaged[offset] ^= 0x10;
+            match lzss.verify_checkpointed(&damaged) {
+                Err(LzssError::CorruptedNear { position }) => {
+                    let expected = tenth as u64 * 10_000;
+                    assert!(position + 20_000 > expected && position <= expected, "damage at {offset} reported near {position}");
+                }
+                other => panic!("damage at {offset}: {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::vec::Vec;
+
+use crate::crc32::crc32;
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+
+/// Length of the header in front of each chunk: its uncompressed length,
+/// its compressed length and the CRC-32 of its uncompressed data, each as
+/// a 4-byte little-endian integer
+const CHUNK_HEADER_LEN: usize = 12;
+
+/// One chunk of a chunked stream, as found by next_chunk()
+struct Chunk<'a> {
+    len: usize,
+    crc: u32,
+    data: &'a [u8],
+}
+
+/// Splits the chunk at the start of `input` off the rest, failing if its
+/// header or compressed data is cut short.
+fn next_chunk(input: &[u8]) -> Result<(Chunk<'_>, &[u8]), LzssError> {
+    let (header, rest) = input.split_first_chunk::<CHUNK_HEADER_LEN>().ok_or(LzssError::TruncatedStream)?;
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let compressed_len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(header[8..].try_into().unwrap());
+    if compressed_len > rest.len() {
+        return Err(LzssError::TruncatedStream);
+    }
+    let (data, rest) = rest.split_at(compressed_len);
+    Ok((Chunk { len, crc, data }, rest))
+}
+
+/// Where one chunk of a chunked stream lies, and whether it checked out,
+/// as reported by verify_chunks().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkStatus {
+    /// offset of the chunk's header in the chunked stream
+    pub offset: u64,
+    /// number of bytes of decompressed data before the chunk's
+    pub position: u64,
+    /// whether the chunk is complete and decompresses to data of the
+    /// length and CRC-32 its header gives
+    pub verified: bool,
+}
+
+impl Lzss {
+    /// Compresses `buffer` as independent chunks of `chunk_size` bytes (the
+    /// last may be shorter), each a complete stream like compress()
+    /// produces behind a header with its lengths and the CRC-32 of its
+    /// data.
+    ///
+    /// Each chunk can be checked on its own, so a transfer that broke off
+    /// or went wrong partway can pick up at the first chunk that doesn't
+    /// verify; see verify_chunks() and decompress_resumable(). As with
+    /// compress_blocks(), no match reaches into an earlier chunk.
+    /// `chunk_size` must be between 1 and `u32::MAX`, otherwise
+    /// `LzssError::InvalidParams` is returned.
+    pub fn compress_chunked(&mut self, buffer: &[u8], chunk_size: usize) -> Result<Vec<u8>, LzssError> {
+        if chunk_size == 0 || chunk_size > u32::MAX as usize {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let mut output = Vec::new();
+        for chunk in buffer.chunks(chunk_size) {
+            let compressed = self.compress(chunk)?;
+            let compressed_len = u32::try_from(compressed.len()).map_err(|_| LzssError::InvalidParams)?;
+            output.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            output.extend_from_slice(&compressed_len.to_le_bytes());
+            output.extend_from_slice(&crc32(chunk).to_le_bytes());
+            output.extend_from_slice(&compressed);
+        }
+        Ok(output)
+    }
+
+    /// Reverse of compress_chunked(). Fails with
+    /// `LzssError::ChecksumMismatch` at the first chunk whose data doesn't
+    /// match its CRC-32.
+    pub fn decompress_chunked(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        self.decompress_resumable(buffer, 0)
+    }
+
+    /// Checks every chunk of a stream made by compress_chunked(), which may
+    /// have been cut short, as by an interrupted transfer. A chunk that is
+    /// cut short is reported as not verified and ends the list.
+    ///
+    /// The first chunk that doesn't verify is where to resume: fetch the
+    /// stream again from its `offset`, and decompress from its `position`
+    /// with decompress_resumable().
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"one chunk, two chunk, three chunk, four".repeat(100);
+    /// let mut lzss = Lzss::new();
+    /// let mut chunked = lzss.compress_chunked(&data, 1000)?;
+    ///
+    /// let second = lzss.verify_chunks(&chunked)[1];
+    /// chunked[second.offset as usize + 20] ^= 0x55;
+    /// let verified: Vec<bool> = lzss.verify_chunks(&chunked).iter().map(|chunk| chunk.verified).collect();
+    /// assert_eq!(verified, [true, false, true, true]);
+    ///
+    /// let rest = lzss.decompress_resumable(&chunked, 2000)?;
+    /// assert_eq!(rest, &data[2000..]);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn verify_chunks(&mut self, buffer: &[u8]) -> Vec<ChunkStatus> {
+        let mut statuses = Vec::new();
+        let mut rest = buffer;
+        let mut position = 0;
+        while !rest.is_empty() {
+            let offset = (buffer.len() - rest.len()) as u64;
+            match next_chunk(rest) {
+                Ok((chunk, tail)) => {
+                    let verified = self.decompress_chunk(&chunk).is_ok();
+                    statuses.push(ChunkStatus { offset, position, verified });
+                    position += chunk.len as u64;
+                    rest = tail;
+                }
+                Err(_) => {
+                    statuses.push(ChunkStatus { offset, position, verified: false });
+                    break;
+                }
+            }
+        }
+        statuses
+    }
+
+    /// Decompresses a stream made by compress_chunked() from `start` bytes
+    /// into its data on, skipping the chunks before without decoding them,
+    /// and verifying each chunk after. `start` must be where a chunk's data
+    /// begins, such as a `position` from verify_chunks(), or the end of the
+    /// data; otherwise `LzssError::InvalidParams` is returned.
+    pub fn decompress_resumable(&mut self, buffer: &[u8], start: u64) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        let mut rest = buffer;
+        let mut position = 0;
+        while !rest.is_empty() {
+            let (chunk, tail) = next_chunk(rest)?;
+            if position >= start {
+                output.extend_from_slice(&self.decompress_chunk(&chunk)?);
+            } else if position + chunk.len as u64 > start {
+                return Err(LzssError::InvalidParams);
+            }
+            position += chunk.len as u64;
+            rest = tail;
+        }
+        if position < start {
+            return Err(LzssError::InvalidParams);
+        }
+        Ok(output)
+    }
+
+    /// Decompresses one chunk and checks its data against the header.
+    fn decompress_chunk(&mut self, chunk: &Chunk<'_>) -> Result<Vec<u8>, LzssError> {
+        // A corrupted stream could decompress to far more than the header
+        // says, so stop at one byte past it.
+        let output = match self.decompress_limited(chunk.data, chunk.len + 1) {
+            Ok(output) if output.len() == chunk.len => output,
+            Ok(_) | Err(LzssError::OutputLimitExceeded) => return Err(LzssError::InvalidBlockIndex),
+            Err(e) => return Err(e),
+        };
+
+        let actual = crc32(&output);
+        if actual != chunk.crc {
+            return Err(LzssError::ChecksumMismatch { expected: chunk.crc, actual });
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::{self, Write};
+
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+
+/// Accepts compressed bytes through `Write`, in chunks of any size, and
+/// writes the decompressed data to an inner writer as it becomes available.
+///
+/// Call finish() once all input is written, to detect a truncated stream and
+/// get the inner writer back.
+#[derive(Clone)]
+pub struct LzssDecoder<W: Write> {
+    inner: W,
+    lzss: Lzss,
+}
+
+impl<W: Write> LzssDecoder<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the decoding.
+    pub fn with_lzss(inner: W, mut lzss: Lzss) -> Self {
+        lzss.decode_begin();
+        Self { inner, lzss }
+    }
+
+    /// Flushes the inner writer and returns it, failing if the compressed
+    /// data stopped in the middle of a unit.
+    pub fn finish(mut self) -> Result<W, LzssError> {
+        self.lzss.decode_finish()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for LzssDecoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lzss.decode_update(buf, &mut self.inner)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_split_decodes_as_one_write() {
+        let data = b"abracadabra, abracadabra, cadabra abra".repeat(20);
+        for lzss in [Lzss::new(), Lzss::with_params(16, 24).unwrap()] {
+            let compressed = lzss.clone().compress(&data).unwrap();
+            for split in 0..=compressed.len() {
+                let mut decoder = LzssDecoder::with_lzss(Vec::new(), lzss.clone());
+                decoder.write_all(&compressed[..split]).unwrap();
+                decoder.write_all(&compressed[split..]).unwrap();
+                assert_eq!(decoder.finish().unwrap(), data, "split at {split}");
+            }
+
+            // And one byte at a time
+            let mut decoder = LzssDecoder::with_lzss(Vec::new(), lzss.clone());
+            for byte in &compressed {
+                decoder.write_all(core::slice::from_ref(byte)).unwrap();
+            }
+            assert_eq!(decoder.finish().unwrap(), data);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Read};
+
+use crate::lzss_stream::Lzss;
+
+/// Wraps a reader of uncompressed data and reads back its compressed form,
+/// so compression can sit anywhere a `Read` is expected:
+///
+/// ```
+/// use std::io;
+/// use lzss::LzssEncoder;
+///
+/// let file: &[u8] = b"hello hello hello";
+/// let mut dest = Vec::new();
+/// io::copy(&mut LzssEncoder::new(file), &mut dest)?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct LzssEncoder<R: Read> {
+    inner: R,
+    lzss: Lzss,
+    /// compressed bytes not yet handed out by read()
+    buf: Vec<u8>,
+    pos: usize,
+    /// whether inner has hit EOF and the final code block is in buf
+    finished: bool,
+}
+
+impl<R: Read> LzssEncoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the encoding.
+    pub fn with_lzss(inner: R, mut lzss: Lzss) -> Self {
+        lzss.encode_begin();
+        Self {
+            inner,
+            lzss,
+            buf: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for LzssEncoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 8192];
+
+        // Encoding a chunk may not complete a code block, so keep going until
+        // there is something to return or nothing left to encode.
+        while self.pos == self.buf.len() && !self.finished {
+            self.buf.clear();
+            self.pos = 0;
+            match self.inner.read(&mut chunk)? {
+                0 => {
+                    self.lzss.encode_finish(&mut self.buf)?;
+                    self.finished = true;
+                }
+                n => self.lzss.encode_update(&chunk[..n], &mut self.buf)?,
+            }
+        }
+
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out its bytes one per read() call.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let Some((&first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            match out.first_mut() {
+                Some(slot) => *slot = first,
+                None => return Ok(0),
+            }
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    /// Reads `encoder` to the end through a buffer of `size` bytes.
+    fn read_all<R: Read>(mut encoder: LzssEncoder<R>, size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut buf = vec![0; size];
+        loop {
+            match encoder.read(&mut buf).unwrap() {
+                0 => return out,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn matches_compress_whatever_the_reads() {
+        let data = b"she sells sea shells by the sea shore, the shells she sells ".repeat(100);
+        let expected = crate::compress(&data).unwrap();
+
+        for size in [1, 7, 64 * 1024] {
+            assert_eq!(read_all(LzssEncoder::new(&data[..]), size), expected, "reading {size} at a time");
+            assert_eq!(read_all(LzssEncoder::new(Trickle(&data)), size), expected, "reading {size} at a time from a trickle");
+        }
+        assert_eq!(read_all(LzssEncoder::new(&b""[..]), 1), b"");
+    }
+}
@@ -0,0 +1,108 @@
+use core::fmt;
+
+/// Everything that can go wrong while compressing or decompressing.
+#[derive(Debug)]
+pub enum LzssError {
+    /// The underlying reader or writer failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The compressed stream ended where more data was promised.
+    TruncatedStream,
+    /// A position-and-length pair refers to bytes that were never decoded.
+    InvalidBackreference { pos: usize, len: usize },
+    /// The window or lookahead cannot be expressed in the encoded format.
+    InvalidParams,
+    /// The decompressed data does not match the checksum stored with it.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The output does not fit in the buffer supplied for it.
+    BufferTooSmall,
+    /// The index of a block stream disagrees with the blocks that follow it.
+    InvalidBlockIndex,
+    /// The input doesn't start with the header of a framed stream.
+    BadMagic,
+    /// The framed stream's header has a version or flags this build doesn't know.
+    UnsupportedHeader { version: u8, flags: u8 },
+    /// A file name for a framed header is empty, too long, not UTF-8, or
+    /// could refer outside the directory it is restored into.
+    InvalidFileName,
+    /// Decompressing would produce more than the caller allowed.
+    OutputLimitExceeded,
+    /// The framed stream was compressed with a different preset dictionary,
+    /// or with none, than the one given for decompressing it.
+    DictionaryMismatch,
+    /// A checkpointed stream is damaged after `position` bytes of
+    /// decompressed data, which checked out, and before the next
+    /// checkpoint.
+    CorruptedNear { position: u64 },
+    /// The caller asked for the compression to stop before it was done.
+    Cancelled,
+    /// The stream decompresses to more or fewer bytes than the length
+    /// stored with it.
+    LengthMismatch { expected: u64 },
+    /// The decompressed data was expected to be text but is not valid
+    /// UTF-8 from `offset` on.
+    InvalidUtf8 { offset: usize },
+}
+
+impl fmt::Display for LzssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LzssError::Io(e) => write!(f, "I/O error: {}", e),
+            LzssError::TruncatedStream => write!(f, "compressed stream is truncated"),
+            LzssError::InvalidBackreference { pos, len } => {
+                write!(f, "invalid back-reference to {} bytes at position {}", len, pos)
+            }
+            LzssError::InvalidParams => write!(f, "invalid compression parameters"),
+            LzssError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: expected {:08x}, got {:08x}", expected, actual)
+            }
+            LzssError::BufferTooSmall => write!(f, "output buffer is too small"),
+            LzssError::InvalidBlockIndex => write!(f, "block index does not match the blocks"),
+            LzssError::BadMagic => write!(f, "not an LZSS stream (bad magic number)"),
+            LzssError::UnsupportedHeader { version, flags } => {
+                write!(f, "unsupported stream header (version {}, flags {:#04x})", version, flags)
+            }
+            LzssError::InvalidFileName => write!(f, "invalid file name in stream header"),
+            LzssError::OutputLimitExceeded => write!(f, "decompressed output exceeds the limit"),
+            LzssError::DictionaryMismatch => write!(f, "stream needs a different preset dictionary"),
+            LzssError::CorruptedNear { position } => {
+                write!(f, "stream is corrupt near decompressed offset {}", position)
+            }
+            LzssError::Cancelled => write!(f, "compression was cancelled"),
+            LzssError::LengthMismatch { expected } => {
+                write!(f, "decompressed length does not match the {} bytes in the header", expected)
+            }
+            LzssError::InvalidUtf8 { offset } => {
+                write!(f, "decompressed data is not valid UTF-8 at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl core::error::Error for LzssError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            LzssError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LzssError {
+    fn from(e: std::io::Error) -> Self {
+        LzssError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<LzssError> for std::io::Error {
+    fn from(e: LzssError) -> Self {
+        match e {
+            LzssError::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::archive::{check_path, ArchiveEntry};
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+use crate::stats::CompressionSummary;
+
+/// Passes reads through, counting the bytes.
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Passes writes through, counting the bytes.
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Lzss {
+    /// Like compress_stream(), with `input` and `output` buffered, so
+    /// handing over a bare `File` is fine. `output` is flushed before this
+    /// returns, so errors writing the end of it aren't lost. Returns the
+    /// number of bytes read and written.
+    pub fn compress_to_writer<R: Read, W: Write>(&mut self, input: R, output: W) -> Result<CompressionSummary, LzssError> {
+        let mut input = CountingReader { inner: BufReader::new(input), count: 0 };
+        let mut output = CountingWriter { inner: BufWriter::new(output), count: 0 };
+
+        self.compress_stream(&mut input, &mut output)?;
+        output.flush()?;
+
+        Ok(CompressionSummary::new(input.count, output.count))
+    }
+
+    /// Reverse of compress_to_writer(). The summary is the same way round,
+    /// the decompressed size first.
+    pub fn decompress_from_reader<R: Read, W: Write>(&mut self, input: R, output: W) -> Result<CompressionSummary, LzssError> {
+        let mut input = CountingReader { inner: BufReader::new(input), count: 0 };
+        let mut output = CountingWriter { inner: BufWriter::new(output), count: 0 };
+
+        self.decompress_stream(&mut input, &mut output)?;
+        output.flush()?;
+
+        Ok(CompressionSummary::new(output.count, input.count))
+    }
+
+    /// Compresses everything `input` yields into a new Vec, for input that
+    /// isn't already in a slice, such as a pipe or a socket of unknown
+    /// length. Only the compressed data is held; it goes straight into the
+    /// Vec, which grows by doubling, so it never holds more than twice
+    /// what is needed.
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use lzss::Lzss;
+    ///
+    /// /// Yields 1, then 2, then 3 bytes and so on, up to 7.
+    /// struct Trickle<'a>(&'a [u8], usize);
+    ///
+    /// impl Read for Trickle<'_> {
+    ///     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    ///         self.1 = self.1 % 7 + 1;
+    ///         let n = self.1.min(buf.len()).min(self.0.len());
+    ///         buf[..n].copy_from_slice(&self.0[..n]);
+    ///         self.0 = &self.0[n..];
+    ///         Ok(n)
+    ///     }
+    /// }
+    ///
+    /// let data = b"a trickle of input, a few bytes at a time".repeat(30);
+    /// let mut lzss = Lzss::new();
+    /// let compressed = lzss.compress_reader_to_vec(Trickle(&data, 0))?;
+    /// assert_eq!(compressed, lzss.compress(&data)?);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_reader_to_vec<R: Read>(&mut self, input: R) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+
+        self.compress_stream(input, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Compresses the file at `input` into a file at `output`, in the
+    /// format of compress(), replacing `output` if it exists. Neither file
+    /// is held in memory.
+    pub fn compress_path<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, input: P, output: Q) -> Result<CompressionSummary, LzssError> {
+        self.compress_to_writer(File::open(input)?, File::create(output)?)
+    }
+
+    /// Reverse of compress_path()
+    pub fn decompress_path<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, input: P, output: Q) -> Result<CompressionSummary, LzssError> {
+        self.decompress_from_reader(File::open(input)?, File::create(output)?)
+    }
+
+    /// Packs every file under `dir` into an archive, as compress_archive()
+    /// does with the entries of ArchiveEntry::read_dir().
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let root = std::env::temp_dir().join(format!("lzss-doc-{}", std::process::id()));
+    /// std::fs::create_dir_all(root.join("tree/sub"))?;
+    /// std::fs::write(root.join("tree/a.txt"), "alpha alpha alpha")?;
+    /// std::fs::write(root.join("tree/sub/b.txt"), "beta")?;
+    ///
+    /// let mut lzss = Lzss::new();
+    /// let archive = lzss.compress_dir(root.join("tree"))?;
+    /// let summary = lzss.extract_archive(&archive, root.join("copy"))?;
+    /// assert_eq!(summary.input_len, 21);
+    /// assert_eq!(std::fs::read(root.join("copy/sub/b.txt"))?, b"beta");
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<u8>, LzssError> {
+        self.compress_archive(&ArchiveEntry::read_dir(dir)?)
+    }
+
+    /// Recreates the files of an archive under `dest`, creating it and
+    /// any directories the paths need. The summary is the same way round
+    /// as decompress_from_reader()'s: the bytes written first, then the
+    /// archive's length.
+    /// Paths are checked before anything is written, so an archive with
+    /// one that could lead outside `dest` writes nothing. Nor is a symbolic
+    /// link under `dest` followed, which could lead outside it just the
+    /// same: meeting one in place of a directory fails with an I/O error
+    /// of kind `InvalidInput`. Existing files are never replaced: meeting
+    /// one fails with an I/O error of kind `AlreadyExists`. On Unix the
+    /// files get the permission bits stored with them.
+    pub fn extract_archive<P: AsRef<Path>>(&mut self, buffer: &[u8], dest: P) -> Result<CompressionSummary, LzssError> {
+        let entries = self.decompress_archive(buffer)?;
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+
+        for entry in &entries {
+            let (dirs, _) = entry.path.rsplit_once('/').unwrap_or_default();
+            create_dirs_under(dest, dirs)?;
+            let path = dest.join(&entry.path);
+            // create_new() doesn't follow a symbolic link at the path
+            let mut file = File::options().write(true).create_new(true).open(&path)?;
+            file.write_all(&entry.data)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(fs::Permissions::from_mode(entry.mode & 0o777))?;
+            }
+        }
+        let written = entries.iter().map(|entry| entry.data.len() as u64).sum();
+        Ok(CompressionSummary::new(written, buffer.len() as u64))
+    }
+}
+
+impl ArchiveEntry {
+    /// Reads every file under `dir`, recursively, in name order, with
+    /// paths relative to `dir`. Only regular files are taken: symbolic
+    /// links are not followed, and empty directories are not kept. A name
+    /// that isn't UTF-8, or that isn't allowed in a path, fails with
+    /// `LzssError::InvalidFileName`.
+    pub fn read_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<ArchiveEntry>, LzssError> {
+        let mut entries = Vec::new();
+        collect_files(dir.as_ref(), "", &mut entries)?;
+        Ok(entries)
+    }
+}
+
+/// Creates the directories of `dirs`, a path with `/` between its
+/// components, under `dest`, one component at a time, so that none of
+/// them can be a symbolic link leading elsewhere.
+fn create_dirs_under(dest: &Path, dirs: &str) -> io::Result<()> {
+    let mut path = dest.to_path_buf();
+    for component in dirs.split('/').filter(|component| !component.is_empty()) {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => {}
+            Ok(metadata) if metadata.is_symlink() => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("{} is a symbolic link", path.display())));
+            }
+            Ok(_) => {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                    format!("{} exists and isn't a directory", path.display())));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&path)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Adds the regular files under `dir` to `entries`, in name order, with
+/// `prefix` and a `/` before their names.
+fn collect_files(dir: &Path, prefix: &str, entries: &mut Vec<ArchiveEntry>) -> Result<(), LzssError> {
+    let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let name = child.file_name().into_string().map_err(|_| LzssError::InvalidFileName)?;
+        let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        check_path(&path)?;
+        // Doesn't follow symbolic links
+        let file_type = child.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&child.path(), &path, entries)?;
+        } else if file_type.is_file() {
+            let metadata = child.metadata()?;
+            #[cfg(unix)]
+            let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777;
+            #[cfg(not(unix))]
+            let mode = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+            entries.push(ArchiveEntry { path, mode, data: fs::read(child.path())? });
+        }
+    }
+    Ok(())
+}
+
+/// Compresses the file at `input` into a file at `output` with the default
+/// parameters:
+///
+/// ```no_run
+/// let summary = lzss::compress_path("notes.txt", "notes.txt.lzss")?;
+/// println!("{}", summary);
+/// # Ok::<(), lzss::LzssError>(())
+/// ```
+pub fn compress_path<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<CompressionSummary, LzssError> {
+    Lzss::new().compress_path(input, output)
+}
+
+/// Decompresses the file at `input`, compressed with the default
+/// parameters, into a file at `output`.
+pub fn decompress_path<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<CompressionSummary, LzssError> {
+    Lzss::new().decompress_path(input, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A new, empty directory of the test's own under the temporary one
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lzss-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn paths_round_trip() {
+        let dir = scratch_dir("paths");
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 1009 * 31 % 256) as u8).collect();
+        fs::write(dir.join("plain"), &data).unwrap();
+
+        let summary = compress_path(dir.join("plain"), dir.join("packed")).unwrap();
+        let packed = fs::read(dir.join("packed")).unwrap();
+        assert_eq!(packed, crate::compress(&data).unwrap());
+        assert_eq!((summary.input_len, summary.output_len), (data.len() as u64, packed.len() as u64));
+
+        let summary = decompress_path(dir.join("packed"), dir.join("unpacked")).unwrap();
+        assert_eq!(fs::read(dir.join("unpacked")).unwrap(), data);
+        assert_eq!((summary.input_len, summary.output_len), (data.len() as u64, packed.len() as u64));
+
+        let missing = compress_path(dir.join("missing"), dir.join("out"));
+        assert!(matches!(missing, Err(LzssError::Io(e)) if e.kind() == io::ErrorKind::NotFound));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extraction_stays_under_the_destination() {
+        let dir = scratch_dir("extract");
+        let dest = dir.join("dest");
+        let entry = |path: &str| ArchiveEntry { path: path.into(), mode: 0o644, data: b"payload".to_vec() };
+        let mut lzss = Lzss::new();
+        let archive = lzss.compress_archive(&[entry("first"), entry("xx/escaped")]).unwrap();
+
+        // compress_archive() refuses a path leading out, so one is put in by hand
+        let at = archive.windows(10).position(|window| window == b"xx/escaped").unwrap();
+        let mut crafted = archive.clone();
+        crafted[at..at + 2].copy_from_slice(b"..");
+        assert!(matches!(lzss.extract_archive(&crafted, &dest), Err(LzssError::InvalidFileName)));
+        assert!(!dir.join("escaped").exists() && !dest.join("first").exists());
+
+        // Nor is a symbolic link where the archive has a directory followed
+        #[cfg(unix)]
+        {
+            fs::create_dir_all(dir.join("outside")).unwrap();
+            fs::create_dir_all(&dest).unwrap();
+            std::os::unix::fs::symlink(dir.join("outside"), dest.join("xx")).unwrap();
+            match lzss.extract_archive(&archive, &dest) {
+                Err(LzssError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+                other => panic!("{other:?}"),
+            }
+            assert_eq!(fs::read_dir(dir.join("outside")).unwrap().count(), 0);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
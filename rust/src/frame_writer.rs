@@ -0,0 +1,81 @@
+use std::io::{self, Write};
+
+use crate::lzss_stream::Lzss;
+
+/// Writer that compresses into a sequence of framed streams, one for each
+/// flush(), as compress_framed() would write them one after another. Each
+/// frame is complete once flush() returns and is never touched again, so a
+/// file being appended to, as by a logger, keeps every flushed frame intact
+/// whatever happens afterwards. Data written since the last flush is held
+/// in memory, since a frame's header records its compressed length.
+///
+/// Frames are compressed independently, so no match reaches into an
+/// earlier one, and flushing very often costs some ratio.
+///
+/// ```
+/// use std::io::Write;
+/// use lzss::{FrameWriter, Lzss};
+///
+/// let mut writer = FrameWriter::new(Vec::new());
+/// writer.write_all(b"first entry\n")?;
+/// writer.flush()?;
+/// writer.write_all(b"second entry\n")?;
+/// writer.flush()?;
+/// writer.write_all(b"third entry\n")?;
+/// let file = writer.finish()?;
+///
+/// let data = Lzss::new().decompress_framed(&file)?;
+/// assert_eq!(data, b"first entry\nsecond entry\nthird entry\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct FrameWriter<W: Write> {
+    inner: W,
+    lzss: Lzss,
+    /// data written since the last frame
+    buf: Vec<u8>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for every
+    /// frame. The parameters go in each frame's header.
+    pub fn with_lzss(inner: W, lzss: Lzss) -> Self {
+        Self { inner, lzss, buf: Vec::new() }
+    }
+
+    /// The writer frames go to, as for calling sync_all() on a file
+    /// after flush().
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writes out a last frame for anything written since the last flush
+    /// and returns the inner writer. Dropping a FrameWriter without this
+    /// loses that data.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for FrameWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// Compresses what was written since the last flush into a frame of its
+    /// own and flushes it to the inner writer. With nothing written, only
+    /// the inner writer is flushed.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let frame = self.lzss.compress_framed(&self.buf)?;
+            self.inner.write_all(&frame)?;
+            self.buf.clear();
+        }
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,563 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::crc32::crc32;
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+use crate::sink::{CountingSink, Sink};
+
+/// First bytes of every framed stream
+const MAGIC: [u8; 4] = *b"LZSS";
+
+/// Version written by compress_framed(), and the newest one read
+const VERSION: u8 = 1;
+
+/// Length of the fixed header: the magic, the version and a flags byte.
+/// Each flag announces an optional field that follows, in bit order.
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// The original file name follows, as a u16 LE length and that many bytes
+/// of UTF-8.
+const FLAG_NAME: u8 = 1 << 0;
+
+/// The original modification time follows, as u64 LE seconds since the Unix
+/// epoch.
+const FLAG_MTIME: u8 = 1 << 1;
+
+/// The byte the ring buffer was filled with follows. Without it the fill
+/// is 0.
+const FLAG_FILL: u8 = 1 << 2;
+
+/// The CRC-32 of the preset dictionary in use follows, as u32 LE. Without
+/// it there was no dictionary.
+const FLAG_DICTIONARY: u8 = 1 << 3;
+
+/// The parameters follow: window bits as a u8, then lookahead and threshold
+/// as u16 LE. Without them they are the defaults of Lzss::new().
+const FLAG_PARAMS: u8 = 1 << 4;
+
+/// The length of the compressed data after the header follows, as u64 LE,
+/// so that more may come after it, as when framed streams are concatenated.
+/// Without it the compressed data runs to the end of the input.
+const FLAG_LENGTH: u8 = 1 << 5;
+
+/// The data after the header is stored as it is, not compressed, because
+/// compressing it would have made it larger. No field follows.
+const FLAG_STORED: u8 = 1 << 6;
+
+/// A second flags byte follows the first, before any field, for the flags
+/// below. With this every bit of the first byte is taken, so new flags go
+/// in the second.
+const FLAG_MORE: u8 = 1 << 7;
+
+/// In the second flags byte: each flag word of the compressed data
+/// announces sixteen units, in two bytes, rather than eight in one. No
+/// field follows.
+const FLAG2_WIDE_FLAGS: u8 = 1 << 0;
+
+/// Every flag of the second byte this version knows
+const KNOWN_FLAGS2: u8 = FLAG2_WIDE_FLAGS;
+
+/// Window bits, lookahead and threshold of Lzss::new()
+const DEFAULT_PARAMS: (u32, usize, usize) = (Lzss::WINDOW_BITS, Lzss::F, Lzss::THRESHOLD);
+
+/// Optional fields stored in the header of a framed stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Name of the file that was compressed, without any directory. Names
+    /// that could lead outside a directory, like `..` or anything with a
+    /// path separator, are rejected when writing and reading.
+    pub name: Option<String>,
+    /// Modification time of the file that was compressed, in seconds since
+    /// the Unix epoch.
+    pub mtime: Option<u64>,
+}
+
+/// How the stream was compressed, as far as decompressing it depends on
+/// settings the header records.
+struct Coding {
+    /// window bits, lookahead and threshold
+    params: (u32, usize, usize),
+    fill: u8,
+    /// units per flag word, 8 or 16
+    flag_bits: u32,
+    dictionary_crc: Option<u32>,
+    /// length of the compressed data, if recorded
+    body_len: Option<u64>,
+    /// whether the data is stored uncompressed, making the rest moot
+    stored: bool,
+}
+
+impl Default for Coding {
+    fn default() -> Self {
+        Self { params: DEFAULT_PARAMS, fill: 0, flag_bits: 8, dictionary_crc: None, body_len: None, stored: false }
+    }
+}
+
+impl Coding {
+    fn of(lzss: &Lzss) -> Self {
+        let dictionary = lzss.dictionary();
+        Self {
+            params: lzss.params(),
+            fill: lzss.fill_byte(),
+            flag_bits: lzss.flag_bits(),
+            dictionary_crc: (!dictionary.is_empty()).then(|| crc32(dictionary)),
+            body_len: None,
+            stored: false,
+        }
+    }
+}
+
+impl FrameHeader {
+    /// Appends the header, including the magic and how the stream was
+    /// compressed, to `output`. The body length, if any, comes last, so it
+    /// can be filled in once the body is written.
+    fn write(&self, coding: &Coding, output: &mut Vec<u8>) -> Result<(), LzssError> {
+        let mut flags = 0;
+        if self.name.is_some() {
+            flags |= FLAG_NAME;
+        }
+        if self.mtime.is_some() {
+            flags |= FLAG_MTIME;
+        }
+        if coding.fill != 0 {
+            flags |= FLAG_FILL;
+        }
+        if coding.dictionary_crc.is_some() {
+            flags |= FLAG_DICTIONARY;
+        }
+        if coding.params != DEFAULT_PARAMS {
+            flags |= FLAG_PARAMS;
+        }
+        if coding.body_len.is_some() {
+            flags |= FLAG_LENGTH;
+        }
+        if coding.stored {
+            flags |= FLAG_STORED;
+        }
+        let mut flags2 = 0;
+        if coding.flag_bits == 16 {
+            flags2 |= FLAG2_WIDE_FLAGS;
+        }
+        if flags2 != 0 {
+            flags |= FLAG_MORE;
+        }
+        output.extend_from_slice(&MAGIC);
+        output.extend_from_slice(&[VERSION, flags]);
+        if flags2 != 0 {
+            output.push(flags2);
+        }
+
+        if let Some(name) = &self.name {
+            check_name(name)?;
+            let len = u16::try_from(name.len()).map_err(|_| LzssError::InvalidFileName)?;
+            output.extend_from_slice(&len.to_le_bytes());
+            output.extend_from_slice(name.as_bytes());
+        }
+        if let Some(mtime) = self.mtime {
+            output.extend_from_slice(&mtime.to_le_bytes());
+        }
+        if coding.fill != 0 {
+            output.push(coding.fill);
+        }
+        if let Some(crc) = coding.dictionary_crc {
+            output.extend_from_slice(&crc.to_le_bytes());
+        }
+        if coding.params != DEFAULT_PARAMS {
+            // Lzss only accepts parameters that fit these fields
+            let (window_bits, lookahead, threshold) = coding.params;
+            output.push(window_bits as u8);
+            output.extend_from_slice(&(lookahead as u16).to_le_bytes());
+            output.extend_from_slice(&(threshold as u16).to_le_bytes());
+        }
+        if let Some(body_len) = coding.body_len {
+            output.extend_from_slice(&body_len.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Parses the header at the start of `buffer`, returning it, how the
+    /// stream was compressed and the number of bytes it took up.
+    fn read(buffer: &[u8]) -> Result<(Self, Coding, usize), LzssError> {
+        let Some((header, mut rest)) = buffer.split_first_chunk::<HEADER_LEN>() else {
+            return Err(LzssError::BadMagic);
+        };
+        if header[..MAGIC.len()] != MAGIC {
+            return Err(LzssError::BadMagic);
+        }
+        let (version, flags) = (header[MAGIC.len()], header[MAGIC.len() + 1]);
+        if version != VERSION {
+            return Err(LzssError::UnsupportedHeader { version, flags });
+        }
+        let mut flags2 = 0;
+        if flags & FLAG_MORE != 0 {
+            let (&more, tail) = rest.split_first().ok_or(LzssError::TruncatedStream)?;
+            if more & !KNOWN_FLAGS2 != 0 {
+                return Err(LzssError::UnsupportedHeader { version, flags: more });
+            }
+            flags2 = more;
+            rest = tail;
+        }
+
+        let mut frame = Self::default();
+        if flags & FLAG_NAME != 0 {
+            let (len, tail) = rest.split_first_chunk::<2>().ok_or(LzssError::TruncatedStream)?;
+            let len = u16::from_le_bytes(*len) as usize;
+            let name = tail.get(..len).ok_or(LzssError::TruncatedStream)?;
+            let name = core::str::from_utf8(name).map_err(|_| LzssError::InvalidFileName)?;
+            check_name(name)?;
+            frame.name = Some(String::from(name));
+            rest = &tail[len..];
+        }
+        if flags & FLAG_MTIME != 0 {
+            let (mtime, tail) = rest.split_first_chunk::<8>().ok_or(LzssError::TruncatedStream)?;
+            frame.mtime = Some(u64::from_le_bytes(*mtime));
+            rest = tail;
+        }
+        let mut coding = Coding::default();
+        if flags & FLAG_FILL != 0 {
+            let (&fill, tail) = rest.split_first().ok_or(LzssError::TruncatedStream)?;
+            coding.fill = fill;
+            rest = tail;
+        }
+        if flags & FLAG_DICTIONARY != 0 {
+            let (crc, tail) = rest.split_first_chunk::<4>().ok_or(LzssError::TruncatedStream)?;
+            coding.dictionary_crc = Some(u32::from_le_bytes(*crc));
+            rest = tail;
+        }
+        if flags & FLAG_PARAMS != 0 {
+            let (params, tail) = rest.split_first_chunk::<5>().ok_or(LzssError::TruncatedStream)?;
+            coding.params = (
+                params[0] as u32,
+                u16::from_le_bytes([params[1], params[2]]) as usize,
+                u16::from_le_bytes([params[3], params[4]]) as usize,
+            );
+            rest = tail;
+        }
+        if flags & FLAG_LENGTH != 0 {
+            let (body_len, tail) = rest.split_first_chunk::<8>().ok_or(LzssError::TruncatedStream)?;
+            coding.body_len = Some(u64::from_le_bytes(*body_len));
+            rest = tail;
+        }
+        coding.stored = flags & FLAG_STORED != 0;
+        if flags2 & FLAG2_WIDE_FLAGS != 0 {
+            coding.flag_bits = 16;
+        }
+
+        Ok((frame, coding, buffer.len() - rest.len()))
+    }
+}
+
+/// Accepts only a single, ordinary path component. With separators ruled
+/// out, `..` can only be the whole name.
+pub(crate) fn check_name(name: &str) -> Result<(), LzssError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(LzssError::InvalidFileName);
+    }
+    Ok(())
+}
+
+impl Lzss {
+    /// Like compress(), but starts the output with a header identifying it
+    /// as LZSS: the magic `b"LZSS"`, a version byte and a flags byte.
+    /// Use compress() for the classic headerless format.
+    ///
+    /// Data that doesn't compress, like JPEG or zip files, is stored as it
+    /// is instead, so the output is never more than a stored frame's header
+    /// larger than the input: 14 bytes, plus the fields of
+    /// compress_framed_with_header(), whatever the parameters.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let noise: Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    /// let mut lzss = Lzss::new();
+    /// let framed = lzss.compress_framed(&noise)?;
+    /// assert!(framed.len() <= noise.len() + 14);
+    /// assert_eq!(lzss.decompress_framed(&framed)?, noise);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_framed(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        self.compress_framed_with_header(buffer, &FrameHeader::default())
+    }
+
+    /// Like compress_framed(), also storing the fields of `header`.
+    pub fn compress_framed_with_header(&mut self, buffer: &[u8], header: &FrameHeader) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        let coding = Coding { body_len: Some(0), ..Coding::of(self) };
+        header.write(&coding, &mut output)?;
+        let header_len = output.len();
+
+        self.encode_slice(buffer, &mut output)?;
+
+        // The whole frames are compared, as the header of the compressed
+        // one may carry fields the stored one leaves out.
+        let mut stored = Vec::new();
+        let coding = Coding { body_len: Some(buffer.len() as u64), stored: true, ..Coding::default() };
+        header.write(&coding, &mut stored)?;
+        if output.len() > stored.len() + buffer.len() {
+            stored.extend_from_slice(buffer);
+            return Ok(stored);
+        }
+        let body_len = (output.len() - header_len) as u64;
+        output[header_len - 8..header_len].copy_from_slice(&body_len.to_le_bytes());
+        Ok(output)
+    }
+
+    /// Like compress_framed_with_header(), but splits `buffer` into blocks
+    /// of `block_size` bytes and compresses them in parallel, as framed
+    /// streams written one after another. Only the first carries `header`.
+    /// decompress_framed() restores the whole.
+    ///
+    /// As with compress_parallel(), no match reaches into an earlier block,
+    /// and the output only depends on `block_size`, not on the number of
+    /// threads. `block_size` must not be 0.
+    ///
+    /// ```
+    /// use lzss::{FrameHeader, Lzss};
+    ///
+    /// let data = b"one block after another ".repeat(1000);
+    /// let header = FrameHeader::default();
+    /// let many = Lzss::new().compress_framed_parallel(&data, &header, 4096)?;
+    /// let one = Lzss::new().with_threads(1).compress_framed_parallel(&data, &header, 4096)?;
+    /// assert_eq!(many, one);
+    /// assert_eq!(Lzss::new().decompress_framed(&many)?, data);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn compress_framed_parallel(&mut self, buffer: &[u8], header: &FrameHeader, block_size: usize) -> Result<Vec<u8>, LzssError> {
+        if block_size == 0 {
+            return Err(LzssError::InvalidParams);
+        }
+        if buffer.len() <= block_size {
+            return self.compress_framed_with_header(buffer, header);
+        }
+
+        let blocks: Vec<&[u8]> = buffer.chunks(block_size).collect();
+        let op = |lzss: &mut Lzss, i: usize, block: &[u8]| match i {
+            0 => lzss.compress_framed_with_header(block, header),
+            _ => lzss.compress_framed(block),
+        };
+        let frames = match self.run_parallel(&blocks, op) {
+            Some(frames) => frames?,
+            None => blocks.iter().enumerate().map(|(i, block)| op(self, i, block)).collect::<Result<_, _>>()?,
+        };
+        Ok(frames.concat())
+    }
+
+    /// Whether `buffer` starts with the magic of a framed stream, as written
+    /// by compress_framed(). Only the magic is checked, so a stream from a
+    /// newer version still counts.
+    pub fn is_framed(buffer: &[u8]) -> bool {
+        buffer.starts_with(&MAGIC)
+    }
+
+    /// Reads the header of a framed stream without decompressing it.
+    pub fn peek_frame_header(buffer: &[u8]) -> Result<FrameHeader, LzssError> {
+        FrameHeader::read(buffer).map(|(header, _, _)| header)
+    }
+
+    /// Reverse of compress_framed(). Fails with `LzssError::BadMagic` if
+    /// `buffer` doesn't start with the header, with
+    /// `LzssError::UnsupportedHeader` if it was written by a newer version,
+    /// and with `LzssError::DictionaryMismatch` unless this instance has the
+    /// preset dictionary the stream was compressed with.
+    ///
+    /// Framed streams written one after another, as by
+    /// compress_framed_parallel() or `cat a.lzss b.lzss`, decompress into
+    /// the concatenation of their data. Anything after a stream that isn't
+    /// another one fails with `LzssError::BadMagic`, rather than being
+    /// left out.
+    pub fn decompress_framed(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        self.decompress_framed_with_header(buffer).map(|(data, _)| data)
+    }
+
+    /// Like decompress_framed(), also returning the header's fields, those
+    /// of the first stream if there are several.
+    ///
+    /// A stream compressed with other parameters, such as those of
+    /// with_level(), is decoded with them. This instance's fill byte and
+    /// dictionary are carried over, and it is left as it was. Decoding
+    /// only needs the window the header asks for, at most 1 MiB, and none
+    /// of the encoder's match finding structures. Parameters
+    /// that no `Lzss` accepts, as from a damaged header, give
+    /// `LzssError::InvalidParams` rather than a wrong decoding.
+    ///
+    /// ```
+    /// use lzss::{Lzss, LzssError};
+    ///
+    /// let data = b"window, lookahead, threshold and fill all travel along".repeat(40);
+    /// let framed = Lzss::with_params(16, 200)?
+    ///     .with_threshold(3)?
+    ///     .with_fill_byte(b' ')
+    ///     .compress_framed(&data)?;
+    /// let (decompressed, _) = Lzss::new().decompress_framed_with_header(&framed)?;
+    /// assert_eq!(decompressed, data);
+    ///
+    /// // Window bits, then lookahead and threshold as u16 LE
+    /// let mut damaged = framed.clone();
+    /// let at = damaged.windows(5).position(|w| w == [16, 200, 0, 3, 0]).unwrap();
+    /// damaged[at] = 40;
+    /// assert!(matches!(Lzss::new().decompress_framed(&damaged), Err(LzssError::InvalidParams)));
+    /// # Ok::<(), LzssError>(())
+    /// ```
+    pub fn decompress_framed_with_header(&mut self, buffer: &[u8]) -> Result<(Vec<u8>, FrameHeader), LzssError> {
+        let mut output = Vec::new();
+        let header = self.decode_frames(buffer, &mut output)?;
+        Ok((output, header))
+    }
+
+    /// Number of bytes decompress_framed() would produce for `buffer`,
+    /// found without keeping the decompressed output, as with
+    /// decompressed_size() for the classic format. It fails as
+    /// decompress_framed() would.
+    pub fn decompressed_size_framed(&mut self, buffer: &[u8]) -> Result<u64, LzssError> {
+        let mut output = CountingSink::default();
+        self.decode_frames(buffer, &mut output)?;
+        Ok(output.len())
+    }
+
+    /// Decodes the framed streams filling `buffer` into `output`, returning
+    /// the first one's header.
+    fn decode_frames<S: Sink>(&mut self, buffer: &[u8], output: &mut S) -> Result<FrameHeader, LzssError> {
+        let (header, len) = self.decode_frame(buffer, output)?;
+        let mut rest = &buffer[len..];
+        while !rest.is_empty() {
+            let (_, len) = self.decode_frame(rest, output)?;
+            rest = &rest[len..];
+        }
+        Ok(header)
+    }
+
+    /// Decodes the framed stream at the start of `buffer` into `output`,
+    /// returning its header and the number of bytes it took up.
+    fn decode_frame<S: Sink>(&mut self, buffer: &[u8], output: &mut S) -> Result<(FrameHeader, usize), LzssError> {
+        let (header, coding, len) = FrameHeader::read(buffer)?;
+        let stream = match coding.body_len {
+            Some(body_len) => usize::try_from(body_len)
+                .ok()
+                .and_then(|body_len| buffer[len..].get(..body_len))
+                .ok_or(LzssError::TruncatedStream)?,
+            None => &buffer[len..],
+        };
+
+        if coding.stored {
+            output.put(stream)?;
+        } else if coding.params == self.params() {
+            self.decode_coded(&coding, stream, output)?;
+        } else {
+            let (window_bits, lookahead, threshold) = coding.params;
+            Lzss::with_params_and_threshold(window_bits, lookahead, threshold)?
+                .with_fill_byte(self.fill_byte())
+                .with_dictionary(self.whole_dictionary())
+                .decode_coded(&coding, stream, output)?;
+        }
+        Ok((header, len + stream.len()))
+    }
+
+    /// Decodes a framed stream's body into `output`, once its parameters are
+    /// known to match this instance's.
+    fn decode_coded<S: Sink>(&mut self, coding: &Coding, stream: &[u8], output: &mut S) -> Result<(), LzssError> {
+        if coding.dictionary_crc != Coding::of(self).dictionary_crc {
+            return Err(LzssError::DictionaryMismatch);
+        }
+
+        // The stream says which fill and flag width it needs; this
+        // instance's own are kept for later calls.
+        let (own_fill, own_flag_bits) = (self.fill_byte(), self.flag_bits());
+        self.set_fill_byte(coding.fill);
+        self.set_flag_bits(coding.flag_bits);
+        let decoded = self.decode_slice(stream, output);
+        self.set_fill_byte(own_fill);
+        self.set_flag_bits(own_flag_bits);
+
+        decoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len as u32).map(|i| (i % 4093 * 7 % 251) as u8 ^ (i >> 14) as u8).collect()
+    }
+
+    #[test]
+    fn every_frame_of_a_multi_frame_file_is_decoded() {
+        let data = sample(300_000);
+        let mut lzss = Lzss::new();
+        let header = FrameHeader { name: Some(String::from("data.bin")), mtime: Some(1) };
+        let mut file = lzss.compress_framed_with_header(&data[..100_000], &header).unwrap();
+        file.extend(Lzss::with_level(6).unwrap().compress_framed(&data[100_000..200_000]).unwrap());
+        file.extend(lzss.compress_framed(&data[200_000..]).unwrap());
+
+        let (decompressed, first) = lzss.decompress_framed_with_header(&file).unwrap();
+        assert!(decompressed == data, "frames after the first are missing");
+        assert_eq!(first, header);
+        assert_eq!(lzss.decompressed_size_framed(&file).unwrap(), data.len() as u64);
+
+        // Trailing bytes that aren't a frame are an error, not ignored
+        file.extend_from_slice(b"junk");
+        assert!(matches!(lzss.decompress_framed(&file), Err(LzssError::BadMagic)));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_frames_decode_with_decompress_framed() {
+        let data = sample(3 << 20);
+        let framed = Lzss::new().compress_framed_parallel(&data, &FrameHeader::default(), 1 << 20).unwrap();
+        let first = Lzss::new().compress_framed(&data[..1 << 20]).unwrap();
+        assert!(framed.starts_with(&first) && framed.len() > first.len());
+        assert!(Lzss::new().decompress_framed(&framed).unwrap() == data);
+    }
+
+    #[test]
+    fn output_is_never_more_than_the_stored_header_larger() {
+        // Noise, which expands, then zeros, which shrink, in proportions
+        // that leave the compressed data just under the input's length
+        let noise = |len: usize| (0..len as u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect::<Vec<u8>>();
+        let header = FrameHeader { name: Some(String::from("x")), mtime: Some(1) };
+        // Every optional field of the coding, which a stored frame leaves out
+        let mut lzss = Lzss::with_params(12, 17).unwrap().with_fill_byte(b' ').with_dictionary(b"zeros").with_flag_bits(16).unwrap();
+        for noise_len in (0..2000).step_by(97) {
+            for zeros in (0..400).step_by(7) {
+                let data = [noise(noise_len), vec![0; zeros]].concat();
+                let framed = lzss.compress_framed_with_header(&data, &header).unwrap();
+                assert!(framed.len() <= data.len() + 14 + 3 + 8, "{} bytes from {noise_len} + {zeros}", framed.len());
+                assert_eq!(lzss.decompress_framed(&framed).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn names_leading_out_of_a_directory_are_rejected() {
+        let mut lzss = Lzss::new();
+        for name in ["..", "../x", "a/b", "a\\b", "", "."] {
+            let header = FrameHeader { name: Some(String::from(name)), mtime: None };
+            assert!(matches!(lzss.compress_framed_with_header(b"data", &header), Err(LzssError::InvalidFileName)), "{name:?}");
+        }
+
+        // Nor are they accepted from a stream, where they are put in by hand
+        let header = FrameHeader { name: Some(String::from("xx")), mtime: None };
+        let mut framed = lzss.compress_framed_with_header(b"data", &header).unwrap();
+        let at = framed.windows(2).position(|window| window == b"xx").unwrap();
+        framed[at..at + 2].copy_from_slice(b"..");
+        assert!(matches!(Lzss::peek_frame_header(&framed), Err(LzssError::InvalidFileName)));
+        assert!(matches!(lzss.decompress_framed_with_header(&framed), Err(LzssError::InvalidFileName)));
+    }
+
+    #[test]
+    fn dictionaries_longer_than_the_decoders_window_still_match() {
+        let data = sample(50_000);
+        let dictionary = sample(8 << 10);
+        let framed = Lzss::with_level(9).unwrap().with_dictionary(&dictionary).compress_framed(&data).unwrap();
+
+        let mut lzss = Lzss::new().with_dictionary(&dictionary);
+        assert!(lzss.decompress_framed(&framed).unwrap() == data);
+        // The default window only has room for the end of the dictionary,
+        // so that end is all its own streams depend on
+        let own = lzss.compress_framed(&data).unwrap();
+        assert!(own == Lzss::new().with_dictionary(&dictionary[(8 << 10) - 2024..]).compress_framed(&data).unwrap());
+        assert!(lzss.decompress_framed(&own).unwrap() == data);
+    }
+}
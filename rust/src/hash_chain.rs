@@ -0,0 +1,138 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::match_finder::MatchFinder;
+
+/// Match finder that hashes the first three bytes of each string and keeps,
+/// for every hash, a chain of the positions that had it, newest first.
+///
+/// Unlike the trees, chains are never unlinked. A link that points at a
+/// position since reused for a newer string is recognized by its distance
+/// from r no longer growing, and the chain is cut there.
+#[derive(Clone)]
+pub struct HashChainMatchFinder {
+    /// size of ring buffer
+    n: usize,
+    /// upper limit for match_length
+    f: usize,
+    /// newest position for each hash, or n
+    head: Vec<usize>,
+    /// next older position with the same hash as each position, or n
+    prev: Vec<usize>,
+    /// hash each position was inserted under
+    hashes: Vec<u16>,
+    /// of longest match. These are set by insert().
+    match_position: usize,
+    match_length: usize,
+    /// most candidates compared for one string
+    max_chain: usize,
+}
+
+impl HashChainMatchFinder {
+    const HASH_BITS: u32 = 14;
+    /// most candidates compared for one string, unless given
+    const MAX_CHAIN: usize = 32;
+
+    pub fn new() -> Self {
+        Self::with_max_chain(Self::MAX_CHAIN)
+    }
+
+    /// Chains that compare at most `max_chain` candidates for each string,
+    /// rather than 32. Longer chains find longer matches, more slowly.
+    pub fn with_max_chain(max_chain: usize) -> Self {
+        Self {
+            n: 0,
+            f: 0,
+            head: vec![0; 1 << Self::HASH_BITS],
+            prev: Vec::new(),
+            hashes: Vec::new(),
+            match_position: 0,
+            match_length: 0,
+            max_chain,
+        }
+    }
+
+    /// Hashes the first three bytes of text_buf[r..], or all F of them if F
+    /// is shorter.
+    fn hash(&self, text_buf: &[u8], r: usize) -> usize {
+        let key = text_buf[r..r + self.f.min(3)].iter().fold(0u32, |key, &c| key << 8 | c as u32);
+        (key.wrapping_mul(0x9e37_79b1) >> (32 - Self::HASH_BITS)) as usize
+    }
+}
+
+impl Default for HashChainMatchFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchFinder for HashChainMatchFinder {
+    fn reset(&mut self, n: usize, f: usize) {
+        self.n = n;
+        self.f = f;
+        self.head.fill(n);
+        self.prev.clear();
+        self.prev.resize(n, n);
+        self.hashes.clear();
+        self.hashes.resize(n, 0);
+        self.match_position = 0;
+        self.match_length = 0;
+    }
+
+    /// Finds the longest match for text_buf[r..r+F-1] among the strings
+    /// behind r, trying at most max_chain of them, then adds r to its chain.
+    fn insert(&mut self, r: usize, text_buf: &[u8]) {
+        let (n, f) = (self.n, self.f);
+        let h = self.hash(text_buf, r);
+
+        self.match_length = 0;
+        let mut p = self.head[h];
+        let mut last_distance = 0;
+        for _ in 0..self.max_chain {
+            if p == n {
+                break;
+            }
+            // Only positions behind r, and not in the F-byte lookahead the
+            // oldest strings are being overwritten by, hold what the decoder
+            // will have.
+            let distance = (r + n - p) & (n - 1);
+            if distance <= last_distance || distance >= n - f {
+                break;
+            }
+            last_distance = distance;
+            debug_assert!(p < n && self.match_length < f);
+
+            // A candidate that differs at match_length can't be longer.
+            if text_buf[p + self.match_length] == text_buf[r + self.match_length] {
+                let i = (0..f).find(|&i| text_buf[p + i] != text_buf[r + i]).unwrap_or(f);
+                if i > self.match_length {
+                    self.match_position = p;
+                    self.match_length = i;
+                    if i >= f {
+                        break;
+                    }
+                }
+            }
+            p = self.prev[p];
+        }
+
+        self.prev[r] = self.head[h];
+        self.head[h] = r;
+        self.hashes[r] = h as u16;
+    }
+
+    fn best_match(&self) -> (usize, usize) {
+        (self.match_position, self.match_length)
+    }
+
+    /// Forgets p, which is about to be overwritten.
+    fn remove(&mut self, p: usize) {
+        let h = self.hashes[p] as usize;
+        if self.head[h] == p {
+            self.head[h] = self.n;
+        }
+    }
+
+    fn clone_box(&self) -> Option<Box<dyn MatchFinder>> {
+        Some(Box::new(self.clone()))
+    }
+}
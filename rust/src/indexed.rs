@@ -0,0 +1,269 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+use crate::sink::CountingSink;
+
+/// Length of the footer ending an indexed stream: the uncompressed length
+/// as an 8-byte and the number of sync points as a 4-byte little-endian
+/// integer
+const FOOTER_LEN: usize = 12;
+
+/// Each sync point's decompressed and compressed offset, as 8-byte
+/// little-endian integers
+const ENTRY_LEN: usize = 16;
+
+/// Compressed bytes fed to the decoder at a time while looking for the end
+/// of a range
+const CHUNK: usize = 4096;
+
+/// A place the decoder can start from other than the beginning
+#[derive(Clone, Copy)]
+struct SyncPoint {
+    /// bytes of decompressed data before it
+    position: u64,
+    /// offset of the code block it starts, from the start of the stream
+    offset: u64,
+}
+
+/// The trailing part of an indexed stream, which locates everything else
+struct Index {
+    len: u64,
+    stream_len: u64,
+    sync_points: Vec<SyncPoint>,
+}
+
+impl Index {
+    /// Works out the layout from the footer and the sync point table, given
+    /// the length of the whole indexed stream and the ring buffer size.
+    fn parse(total_len: u64, footer: &[u8; FOOTER_LEN], table: &[u8], ring_len: usize) -> Result<Self, LzssError> {
+        let len = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        let count = table.len() / ENTRY_LEN;
+        let stream_len = (ring_len as u64)
+            .checked_mul(count as u64)
+            .and_then(|rings| total_len.checked_sub(FOOTER_LEN as u64 + table.len() as u64 + rings))
+            .ok_or(LzssError::TruncatedStream)?;
+
+        let sync_points = table
+            .chunks_exact(ENTRY_LEN)
+            .map(|entry| SyncPoint {
+                position: u64::from_le_bytes(entry[..8].try_into().unwrap()),
+                offset: u64::from_le_bytes(entry[8..].try_into().unwrap()),
+            })
+            .collect::<Vec<_>>();
+        // Sync points must be in order and inside the data they index
+        let mut last = SyncPoint { position: 0, offset: 0 };
+        for &point in &sync_points {
+            if point.position < last.position || point.offset < last.offset || point.position > len || point.offset > stream_len {
+                return Err(LzssError::InvalidBlockIndex);
+            }
+            last = point;
+        }
+
+        Ok(Self { len, stream_len, sync_points })
+    }
+
+    /// Number of sync points the footer announces
+    fn count(footer: &[u8; FOOTER_LEN]) -> usize {
+        u32::from_le_bytes(footer[8..].try_into().unwrap()) as usize
+    }
+
+    /// The last sync point at or before `position`, with its number, if any.
+    fn sync_point_before(&self, position: u64) -> Option<(usize, SyncPoint)> {
+        let i = self.sync_points.partition_point(|point| point.position <= position);
+        i.checked_sub(1).map(|i| (i, self.sync_points[i]))
+    }
+
+    /// Checks that `start..start + len` lies inside the decompressed data.
+    fn check_range(&self, start: u64, len: usize) -> Result<(), LzssError> {
+        match start.checked_add(len as u64) {
+            Some(end) if end <= self.len => Ok(()),
+            _ => Err(LzssError::InvalidParams),
+        }
+    }
+}
+
+impl Lzss {
+    /// Compresses `buffer` like compress(), followed by an index that lets
+    /// decompress_range() start decoding partway through instead of at the
+    /// beginning.
+    ///
+    /// Roughly every `interval` bytes of input, at the next code block, the
+    /// index records a sync point: the offsets in the compressed and
+    /// decompressed data, and the decoder's whole ring buffer. Each sync
+    /// point therefore costs N + 16 bytes, 2064 with the default
+    /// parameters, so `interval` should be many times N. A shorter interval
+    /// means less to decode before a range starts. `interval` must not be 0.
+    ///
+    /// Unlike compress_blocks(), matches still reach across sync points, so
+    /// the stream itself is exactly what compress() produces.
+    pub fn compress_indexed(&mut self, buffer: &[u8], interval: usize) -> Result<Vec<u8>, LzssError> {
+        if interval == 0 {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let mut output = self.compress(buffer)?;
+        let stream_len = output.len();
+
+        // Decode what was just written one code block at a time, saving the
+        // decoder's state at the boundaries wanted.
+        let mut sync_points = Vec::new();
+        let mut rings = Vec::new();
+        let mut position = 0;
+        let mut next = interval;
+        let mut offset = 0;
+        self.decode_begin();
+        while offset < stream_len {
+            if position >= next {
+                sync_points.push(SyncPoint { position: position as u64, offset: offset as u64 });
+                rings.extend_from_slice(self.ring());
+                next = position + interval;
+            }
+
+            let block_len = self.code_block_len(&output[offset..stream_len]);
+            let mut decoded = CountingSink::default();
+            self.decode_update(&output[offset..offset + block_len], &mut decoded)?;
+            position += decoded.len() as usize;
+            offset += block_len;
+        }
+
+        output.extend_from_slice(&rings);
+        for point in &sync_points {
+            output.extend_from_slice(&point.position.to_le_bytes());
+            output.extend_from_slice(&point.offset.to_le_bytes());
+        }
+        let count = u32::try_from(sync_points.len()).map_err(|_| LzssError::InvalidParams)?;
+        output.extend_from_slice(&(buffer.len() as u64).to_le_bytes());
+        output.extend_from_slice(&count.to_le_bytes());
+        Ok(output)
+    }
+
+    /// Reverse of compress_indexed(), decompressing everything. Fails with
+    /// `LzssError::InvalidBlockIndex` if the index doesn't match the data.
+    pub fn decompress_indexed(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let index = self.parse_index(buffer)?;
+
+        let output = self.decompress(&buffer[..index.stream_len as usize])?;
+        if output.len() as u64 != index.len {
+            return Err(LzssError::InvalidBlockIndex);
+        }
+        Ok(output)
+    }
+
+    /// Decompresses `len` bytes from `start` on out of a stream made by
+    /// compress_indexed(), decoding only from the last sync point before
+    /// `start`. Fails with `LzssError::InvalidParams` if the range reaches
+    /// past the end of the data.
+    pub fn decompress_range(&mut self, buffer: &[u8], start: u64, len: usize) -> Result<Vec<u8>, LzssError> {
+        let index = self.parse_index(buffer)?;
+        index.check_range(start, len)?;
+
+        let stream = &buffer[..index.stream_len as usize];
+        let (from, offset) = match index.sync_point_before(start) {
+            Some((i, point)) => {
+                let ring_len = self.ring().len();
+                let rings = &buffer[stream.len()..];
+                self.decode_resume(&rings[i * ring_len..(i + 1) * ring_len], point.position);
+                (point.position, point.offset as usize)
+            }
+            None => {
+                self.decode_begin();
+                (0, 0)
+            }
+        };
+
+        let skip = (start - from) as usize;
+        let mut output = Vec::new();
+        for chunk in stream[offset..].chunks(CHUNK) {
+            if output.len() >= skip + len {
+                break;
+            }
+            self.decode_update(chunk, &mut output)?;
+        }
+        Self::cut_range(output, skip, len)
+    }
+
+    /// Like decompress_range(), reading the indexed stream from `input`
+    /// instead of memory. Only the index, one sync point's ring buffer and
+    /// the compressed data from that sync point up to the end of the range
+    /// are read, so ranges of files too large to hold are cheap to get.
+    #[cfg(feature = "std")]
+    pub fn decompress_range_from<R: Read + Seek>(&mut self, mut input: R, start: u64, len: usize) -> Result<Vec<u8>, LzssError> {
+        let total_len = input.seek(SeekFrom::End(0))?;
+        if total_len < FOOTER_LEN as u64 {
+            return Err(LzssError::TruncatedStream);
+        }
+        let mut footer = [0u8; FOOTER_LEN];
+        input.seek(SeekFrom::Start(total_len - FOOTER_LEN as u64))?;
+        input.read_exact(&mut footer)?;
+
+        let table_len = Index::count(&footer) as u64 * ENTRY_LEN as u64;
+        let table_start = (total_len - FOOTER_LEN as u64).checked_sub(table_len).ok_or(LzssError::TruncatedStream)?;
+        let mut table = vec![0u8; table_len as usize];
+        input.seek(SeekFrom::Start(table_start))?;
+        input.read_exact(&mut table)?;
+
+        let ring_len = self.ring().len();
+        let index = Index::parse(total_len, &footer, &table, ring_len)?;
+        index.check_range(start, len)?;
+
+        let (from, offset) = match index.sync_point_before(start) {
+            Some((i, point)) => {
+                let mut ring = vec![0u8; ring_len];
+                input.seek(SeekFrom::Start(index.stream_len + (i * ring_len) as u64))?;
+                input.read_exact(&mut ring)?;
+                self.decode_resume(&ring, point.position);
+                (point.position, point.offset)
+            }
+            None => {
+                self.decode_begin();
+                (0, 0)
+            }
+        };
+
+        let skip = (start - from) as usize;
+        let mut output = Vec::new();
+        input.seek(SeekFrom::Start(offset))?;
+        let mut stream = input.take(index.stream_len - offset);
+        let mut chunk = [0u8; CHUNK];
+        while output.len() < skip + len {
+            match stream.read(&mut chunk)? {
+                0 => break,
+                n => self.decode_update(&chunk[..n], &mut output)?,
+            }
+        }
+        Self::cut_range(output, skip, len)
+    }
+
+    /// Reads the footer and sync point table at the end of `buffer`.
+    fn parse_index(&self, buffer: &[u8]) -> Result<Index, LzssError> {
+        let (rest, footer) = buffer.split_last_chunk::<FOOTER_LEN>().ok_or(LzssError::TruncatedStream)?;
+        let table_len = Index::count(footer).checked_mul(ENTRY_LEN).ok_or(LzssError::TruncatedStream)?;
+        let table_start = rest.len().checked_sub(table_len).ok_or(LzssError::TruncatedStream)?;
+
+        Index::parse(buffer.len() as u64, footer, &rest[table_start..], self.ring().len())
+    }
+
+    /// Number of bytes taken by the code block at the start of `stream`: the
+    /// flag word and the units it announces, or as many as are left.
+    pub(crate) fn code_block_len(&self, stream: &[u8]) -> usize {
+        let Some((flags, _)) = self.split_flags(stream) else {
+            return stream.len();
+        };
+        let units: usize = (0..self.flag_bits()).map(|bit| if flags >> bit & 1 != 0 { 1 } else { self.pair_len() }).sum();
+        (self.flag_bytes() + units).min(stream.len())
+    }
+
+    /// Takes the `len` bytes after the first `skip` out of `output`, which
+    /// holds everything decoded from a sync point on.
+    fn cut_range(mut output: Vec<u8>, skip: usize, len: usize) -> Result<Vec<u8>, LzssError> {
+        if output.len() < skip + len {
+            return Err(LzssError::TruncatedStream);
+        }
+        output.truncate(skip + len);
+        output.drain(..skip);
+        Ok(output)
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::{self, ErrorKind, Read};
+
+use crate::lzss_stream::Lzss;
+
+/// Compressed input is read this much at a time, which bounds how far
+/// decoding runs ahead of the bytes asked for.
+const CHUNK: usize = 512;
+
+/// Iterates over the decompressed bytes of a compressed reader, decoding
+/// only as far as the bytes asked for, so scanning for something can stop
+/// without decompressing the rest:
+///
+/// ```
+/// use lzss::{compress, decompress_iter};
+///
+/// let compressed = compress(b"needle in a haystack of hay hay hay")?;
+/// let first: Vec<u8> = decompress_iter(&compressed[..]).take(6).collect::<Result<_, _>>()?;
+/// assert_eq!(first, b"needle");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// After an error the iterator ends.
+pub struct DecompressIter<R: Read> {
+    inner: R,
+    lzss: Lzss,
+    /// decoded bytes not yet handed out by next()
+    buf: Vec<u8>,
+    pos: usize,
+    /// whether inner has hit EOF or failed
+    finished: bool,
+}
+
+impl<R: Read> DecompressIter<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the decoding.
+    pub fn with_lzss(inner: R, mut lzss: Lzss) -> Self {
+        lzss.decode_begin();
+        Self {
+            inner,
+            lzss,
+            buf: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Decodes input until some output is ready. Ok(false) means the end
+    /// of the stream.
+    fn refill(&mut self) -> io::Result<bool> {
+        let mut chunk = [0u8; CHUNK];
+
+        self.buf.clear();
+        self.pos = 0;
+        while self.buf.is_empty() {
+            let n = match self.inner.read(&mut chunk) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 { // EOF
+                self.lzss.decode_finish()?;
+                return Ok(false);
+            }
+            self.lzss.decode_update(&chunk[..n], &mut self.buf)?;
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for DecompressIter<R> {
+    type Item = io::Result<u8>;
+
+    fn next(&mut self) -> Option<io::Result<u8>> {
+        if self.pos == self.buf.len() {
+            if self.finished {
+                return None;
+            }
+            match self.refill() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.finished = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let c = self.buf[self.pos];
+        self.pos += 1;
+        Some(Ok(c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the bytes read through it.
+    struct Metered<'a> {
+        data: &'a [u8],
+        read: usize,
+    }
+
+    impl Read for Metered<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let n = self.data.read(out)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn collects_to_what_decompress_gives() {
+        let data: Vec<u8> = (0..40_000u32).map(|i| (i * i % 251) as u8).collect();
+        let compressed = crate::compress(&data).unwrap();
+        let collected: Vec<u8> = DecompressIter::new(&compressed[..]).collect::<io::Result<_>>().unwrap();
+        assert_eq!(collected, crate::decompress(&compressed).unwrap());
+        assert_eq!(DecompressIter::new(&b""[..]).count(), 0);
+    }
+
+    #[test]
+    fn stops_reading_once_enough_is_decoded() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 7919) as u8 ^ (i / 7919) as u8).collect();
+        let compressed = crate::compress(&data).unwrap();
+        let mut input = Metered { data: &compressed, read: 0 };
+        let first: Vec<u8> = DecompressIter::new(&mut input).take(100).collect::<io::Result<_>>().unwrap();
+        assert_eq!(first, data[..100]);
+        assert_eq!(input.read, CHUNK);
+        assert!(compressed.len() > 10 * CHUNK);
+    }
+
+    #[test]
+    fn truncated_input_ends_with_an_error() {
+        let compressed = crate::compress(b"the end is cut off, the end is cut off").unwrap();
+        let mut iter = DecompressIter::new(&compressed[..compressed.len() - 1]);
+        let last = iter.by_ref().find(|c| c.is_err());
+        assert!(last.is_some());
+        assert!(iter.next().is_none());
+    }
+}
@@ -0,0 +1,77 @@
+//! LZSS compression, after Haruhiko Okumura's LZSS.C.
+//!
+//! [`compress`] and [`decompress`] cover the common case; [`Lzss`] allows
+//! tuning the parameters and reusing buffers across calls. Without the
+//! default `std` feature the crate only needs `core` and `alloc`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod autotune;
+mod blocks;
+#[cfg(feature = "checkpointed")]
+mod checkpointed;
+mod chunked;
+mod crc32;
+#[cfg(feature = "std")]
+mod decoder;
+#[cfg(feature = "std")]
+mod encoder;
+mod error;
+#[cfg(feature = "std")]
+mod files;
+#[cfg(feature = "std")]
+mod frame_writer;
+mod framed;
+mod hash_chain;
+mod indexed;
+#[cfg(feature = "std")]
+mod iter;
+mod lzss_stream;
+mod match_finder;
+mod optimal;
+#[cfg(feature = "std")]
+mod pool;
+mod records;
+mod sink;
+mod stats;
+mod step;
+mod token;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "std")]
+mod writer;
+
+pub use crate::archive::ArchiveEntry;
+#[cfg(feature = "tokio")]
+pub use crate::async_io::{AsyncLzssDecoder, AsyncLzssEncoder};
+pub use crate::autotune::LzssParams;
+#[cfg(feature = "std")]
+pub use crate::decoder::LzssDecoder;
+#[cfg(feature = "std")]
+pub use crate::encoder::LzssEncoder;
+pub use crate::chunked::ChunkStatus;
+pub use crate::error::LzssError;
+#[cfg(feature = "std")]
+pub use crate::files::{compress_path, decompress_path};
+#[cfg(feature = "std")]
+pub use crate::frame_writer::FrameWriter;
+pub use crate::framed::FrameHeader;
+pub use crate::hash_chain::HashChainMatchFinder;
+#[cfg(feature = "std")]
+pub use crate::iter::DecompressIter;
+pub use crate::lzss_stream::{compress, decompress, decompress_limited, decompress_to_string, Lzss};
+#[cfg(feature = "std")]
+pub use crate::lzss_stream::decompress_iter;
+pub use crate::match_finder::{BstMatchFinder, MatchFinder, MatchStrategy};
+#[cfg(feature = "std")]
+pub use crate::pool::{LzssPool, PooledLzss};
+pub use crate::stats::{CompressionStats, CompressionSummary};
+pub use crate::step::StepEncoder;
+pub use crate::token::Token;
+#[cfg(feature = "std")]
+pub use crate::writer::LzssWriter;
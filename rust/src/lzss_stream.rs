@@ -1,5 +1,19 @@
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
 use std::io::{Read, Write};
 
+use crate::crc32::crc32;
+use crate::error::LzssError;
+use crate::hash_chain::HashChainMatchFinder;
+#[cfg(feature = "std")]
+use crate::iter::DecompressIter;
+use crate::match_finder::{self, MatchFinder, MatchStrategy};
+use crate::optimal;
+use crate::sink::{CountingSink, LimitedSink, Sink, SliceSink};
+use crate::stats::{CompressionStats, CompressionSummary};
+use crate::token::Token;
+
 /// LZSS.C -- A Data Compression Program
 /// (tab = 4 spaces)
 /// 
@@ -11,359 +25,1789 @@ use std::io::{Read, Write};
 ///     CompuServe  74050,1022
 pub struct Lzss {
     /// size of ring buffer
-    text_buf: [u8; Self::N + Self::F - 1],
+    n: usize,
+    /// upper limit for match_length
+    f: usize,
+    /// number of bits a position takes in an encoded position-and-length pair
+    window_bits: u32,
+    /// encode string into position and length if match_length is greater than this
+    threshold: usize,
+    /// whether to look one byte ahead for a longer match before sending one
+    lazy_matching: bool,
+    /// whether compressing a whole slice plans the units in a first pass
+    optimal_parsing: bool,
+    /// number of units announced by each flag word, 8 or 16
+    flag_bits: u32,
+    /// most threads compress_parallel() and the like use; 0 for one per core
+    #[cfg(feature = "parallel")]
+    threads: usize,
+    /// byte text_buf is filled with before encoding or decoding
+    fill: u8,
+    /// preset dictionary as given, up to the largest window; only its last
+    /// N - F bytes are preloaded just behind r. Empty for none
+    dictionary: Vec<u8>,
+    /// ring buffer of size N, with extra F-1 bytes to facilitate string
+    /// comparison; empty until the first compression, as decompressing
+    /// doesn't use it
+    text_buf: Vec<u8>,
     /// of longest match. These are set by the insert_node() procedure.
     match_position: usize,
     match_length: usize,
-    /// left & right children & parents -- These constitute binary search trees.
-    lson: [usize; Self::N + 1],
-    rson: [usize; Self::N + 257],
-    dad: [usize; Self::N + 1],
+    /// finds earlier occurrences of the string at r; the binary search trees by default
+    finder: Box<dyn MatchFinder>,
+    /// which built-in finder is in use, or None for one from with_match_finder()
+    strategy: Option<MatchStrategy>,
+    /// most candidates the built-in finder compares per string, or None for
+    /// its own default
+    max_chain: Option<usize>,
+    /// whether the trees break ties between equally long matches by distance
+    nearest_matches: bool,
+    /// progress of the encoder between encode_update() calls
+    enc: EncodeState,
+    /// progress of the decoder between decode_update() calls
+    dec: DecodeState,
+    /// whether a decompression was begun more recently than a compression
+    decoding: bool,
+}
+
+/// Everything encode() used to keep in locals, so the input can arrive in pieces
+#[derive(Clone)]
+struct EncodeState {
+    /// oldest position in the ring buffer, overwritten by the next byte read
+    s: usize,
+    /// position of the string being encoded
+    r: usize,
+    /// number of bytes in text_buf[r..] still to be encoded
+    len: usize,
+    /// number of bytes behind r holding the dictionary or encoded data
+    /// rather than the fill, at most N - F
+    history: usize,
+    /// code_buf[1..25] saves eight units of code, and
+    /// code_buf[0] works as eight flags, "1" representing that the unit
+    /// is an unencoded letter (1 byte), "0" a position-and-length pair
+    /// (2 or 3 bytes). Thus, eight units require at most 24 bytes of code.
+    /// With 16-bit flags code_buf[0..2] holds sixteen flags, low byte
+    /// first, and up to 48 bytes of code follow.
+    code_buf: [u8; 50],
+    code_buf_ptr: usize,
+    mask: u8,
+    /// the flag byte mask is working through
+    flag_byte: usize,
+    /// bytes to read before the next unit can be sent
+    pending: usize,
+    /// whether the first F bytes have been read and inserted
+    started: bool,
+    /// position and length of a match at r - 1 held back by lazy matching
+    deferred: Option<(usize, usize)>,
+    /// counts of what was sent, when compress_with_stats() asked for them
+    stats: Option<CompressionStats>,
+    /// every unit sent, when compress_with_tokens() asked for them
+    tokens: Option<Vec<Token>>,
+    /// number of input bytes before r
+    pos: usize,
+    /// the longest match found at each position, when planning a parse
+    longest: Option<Vec<u16>>,
+    /// the length of the unit to send at each position, from optimal::plan()
+    plan: Option<Vec<u16>>,
+    /// whether compress_update() began this encoding, for compress_finish()
+    /// to end
+    incremental: bool,
+    /// where units must start, when compress_records() asked for that
+    unit_starts: Option<UnitStarts>,
+}
+
+/// Input positions the encoder must start a unit at, and where in the code
+/// the units it started there went
+#[derive(Clone, Default)]
+struct UnitStarts {
+    /// positions still to come, soonest last
+    pending: Vec<usize>,
+    /// number of bytes of code in the code blocks sent so far
+    sent: usize,
+    /// offset in the code of the unit at each position passed
+    offsets: Vec<usize>,
+}
+
+/// Progress of a decode() that may be fed its input in pieces. The
+/// decoder keeps a ring buffer of its own, of just N bytes, so that
+/// decompressing neither disturbs a compression under way nor sets up the
+/// encoder's buffer and match finder.
+#[derive(Clone, Default)]
+struct DecodeState {
+    /// ring buffer of size N; empty until the first decompression
+    ring: Vec<u8>,
+    /// position the next decoded byte goes to
+    r: usize,
+    /// flags byte being worked through, with a count of the units left in the higher byte
+    flags: u32,
+    /// number of bytes of a 16-bit flag word read so far
+    flag_bytes: usize,
+    /// whether the flags byte was read but none of its units yet
+    fresh_flags: bool,
+    /// leading bytes of a position-and-length pair whose last byte hasn't arrived
+    pair: [u8; 2],
+    pair_filled: usize,
+    /// Number of bytes behind r that a position-and-length pair may refer to.
+    /// encode() primes its trees with the F bytes before r, and any preset
+    /// dictionary, so those count as populated from the start.
+    populated: usize,
+    /// number of bytes behind r holding the dictionary or decoded data
+    /// rather than the fill, at most N
+    history: usize,
+    /// bytes copied by the current position-and-length pair
+    unit: Vec<u8>,
+}
+
+impl Default for EncodeState {
+    fn default() -> Self {
+        Self {
+            s: 0,
+            r: 0,
+            len: 0,
+            history: 0,
+            code_buf: [0; 50],
+            code_buf_ptr: 1,
+            mask: 1,
+            flag_byte: 0,
+            pending: 0,
+            started: false,
+            deferred: None,
+            stats: None,
+            tokens: None,
+            pos: 0,
+            longest: None,
+            plan: None,
+            incremental: false,
+            unit_starts: None,
+        }
+    }
 }
 
 impl Lzss {
-    const N: usize = 2048; // size of ring buffer
-    const F: usize = 24;   // upper limit for match_length
-    const THRESHOLD: usize = 1; // encode string into position and length if match_length is greater than this
-    const NIL: usize = Self::N; // index for root of binary search trees
+    pub(crate) const WINDOW_BITS: u32 = 11; // default size of ring buffer is 1 << WINDOW_BITS
+    pub(crate) const F: usize = 24;   // default upper limit for match_length
+    pub(crate) const THRESHOLD: usize = 1; // default for threshold
+    const HEADER_LEN: usize = 8; // length prefix written by compress_with_header()
+    const CHECKSUM_LEN: usize = 4; // CRC-32 prefix written by compress_checked()
+    const CANCEL_CHECK_INTERVAL: usize = 4096; // input bytes between checks in compress_cancellable()
 
+    /// Creates a compressor with the default parameters: a 2048-byte ring
+    /// buffer and matches of up to 24 bytes.
+    ///
+    /// The 5-bit length field could express matches of up to 33 bytes, but
+    /// the lookahead also decides where in the ring buffer decoding starts,
+    /// so raising the default would make every existing stream decode
+    /// wrongly. `with_params(11, 33)` uses the whole field; on text that is
+    /// about 3.5% smaller, and on long runs of one byte over 25%.
     pub fn new() -> Self {
-        Self {
-            text_buf: [0; Self::N + Self::F - 1],
+        Self::with_params(Self::WINDOW_BITS, Self::F).expect("default parameters are valid")
+    }
+
+    /// Creates a compressor with a ring buffer of `1 << window_bits` bytes and
+    /// matches of up to `lookahead` bytes.
+    ///
+    /// A position-and-length pair is 2 bytes for `window_bits` up to 15, as
+    /// in LZSS.C, and 3 bytes from 16 up. Its first bytes hold the low 8 bits
+    /// of the position each; the last holds the rest of the position above
+    /// the length, which gets whatever bits are left: `16 - window_bits` or
+    /// `24 - window_bits`. `lookahead` is bounded by what that length field
+    /// can express and by half the ring buffer. `window_bits` must be between
+    /// 8 and 20. Anything else is rejected with `LzssError::InvalidParams`.
+    /// Streams compressed with custom parameters can only be decompressed by
+    /// an `Lzss` built with the same parameters, because the stream itself does
+    /// not record them.
+    pub fn with_params(window_bits: u32, lookahead: usize) -> Result<Self, LzssError> {
+        Self::with_params_and_threshold(window_bits, lookahead, Self::THRESHOLD)
+    }
+
+    /// Creates a compressor with the parameters of LZSS.C: a 4096-byte ring
+    /// buffer, matches of 3 to 18 bytes, and the ring buffer cleared to
+    /// spaces before each compression or decompression. It compresses to
+    /// the very bytes LZSS.C does, and decompresses what LZSS.C wrote.
+    ///
+    /// `testdata/` holds LZSS.C's own source and what LZSS.C compresses it
+    /// to:
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let source = include_bytes!("../testdata/lzss.c.txt");
+    /// let golden = include_bytes!("../testdata/lzss.c.txt.lzss");
+    /// let mut lzss = Lzss::lzss_c();
+    /// assert_eq!(lzss.compress(source)?, golden);
+    /// assert_eq!(lzss.decompress(golden)?, source);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn lzss_c() -> Self {
+        Self::with_params_and_threshold(12, 18, 2)
+            .expect("LZSS.C's parameters are valid")
+            .with_fill_byte(b' ')
+    }
+
+    /// Like with_params(), also setting the threshold. Because the length
+    /// field holds `length - (threshold + 1)`, a higher threshold allows a
+    /// longer lookahead, as in LZSS.C, which with_threshold() can't give
+    /// once the lookahead is set.
+    pub(crate) fn with_params_and_threshold(window_bits: u32, lookahead: usize, threshold: usize) -> Result<Self, LzssError> {
+        if !(8..=20).contains(&window_bits) {
+            return Err(LzssError::InvalidParams);
+        }
+
+        let n = 1usize << window_bits;
+        let max_lookahead = (1usize << Self::length_bits_for(window_bits)) + threshold;
+        if threshold == 0 || lookahead <= threshold || lookahead > max_lookahead || lookahead * 2 > n {
+            return Err(LzssError::InvalidParams);
+        }
+
+        Ok(Self {
+            n,
+            f: lookahead,
+            window_bits,
+            threshold,
+            lazy_matching: false,
+            optimal_parsing: false,
+            flag_bits: 8,
+            #[cfg(feature = "parallel")]
+            threads: 0,
+            fill: 0,
+            dictionary: Vec::new(),
+            text_buf: Vec::new(),
             match_position: 0,
             match_length: 0,
-            lson: [0; Self::N + 1],
-            rson: [0; Self::N + 257],  
-            dad: [0; Self::N + 1],
+            finder: match_finder::tree_finder(n, usize::MAX, false),
+            strategy: Some(MatchStrategy::BinaryTree),
+            max_chain: None,
+            nearest_matches: false,
+            enc: EncodeState::default(),
+            dec: DecodeState::default(),
+            decoding: false,
+        })
+    }
+
+    /// Creates a compressor tuned by an effort level from 1, fastest, to 9,
+    /// smallest output, like gzip's. Low levels use a small window, the
+    /// hash chains and greedy matching; higher ones a window of up to 256
+    /// KiB, longer matches and lazy matching, and the top two the binary
+    /// trees. None of them is the default of new(), which keeps the
+    /// classic format. Levels outside 1..=9 give `LzssError::InvalidParams`.
+    ///
+    /// The parameters are only known to the decompressor if they travel
+    /// with the stream, as in compress_framed().
+    pub fn with_level(level: u32) -> Result<Self, LzssError> {
+        let (window_bits, lookahead, strategy, lazy) = match level {
+            1 => (10, 18, MatchStrategy::HashChain, false),
+            2 => (11, 18, MatchStrategy::HashChain, false),
+            3 => (11, 33, MatchStrategy::HashChain, false),
+            4 => (11, 33, MatchStrategy::HashChain, true),
+            5 => (16, 32, MatchStrategy::HashChain, false),
+            6 => (16, 64, MatchStrategy::HashChain, true),
+            7 => (17, 129, MatchStrategy::HashChain, true),
+            8 => (18, 65, MatchStrategy::BinaryTree, true),
+            9 => (17, 129, MatchStrategy::BinaryTree, true),
+            _ => return Err(LzssError::InvalidParams),
+        };
+        Ok(Self::with_params(window_bits, lookahead)?
+            .with_match_strategy(strategy)
+            .with_lazy_matching(lazy))
+    }
+
+    /// Sends matches of `threshold` bytes or fewer as literals instead.
+    /// A higher threshold makes short matches, which save little or nothing,
+    /// cheaper to decode and lets each length code stand for a longer match.
+    /// The default is 1. `threshold` must be at least 1 and below the
+    /// lookahead, and low enough that the lookahead still fits the length
+    /// field, otherwise `LzssError::InvalidParams` is returned.
+    /// As with the other parameters, the decompressor must use the same
+    /// threshold.
+    pub fn with_threshold(mut self, threshold: usize) -> Result<Self, LzssError> {
+        if threshold == 0 || threshold >= self.f || self.f > (1 << self.length_bits()) + threshold {
+            return Err(LzssError::InvalidParams);
         }
+        self.threshold = threshold;
+        Ok(self)
+    }
+
+    /// Chooses how matches are found. The default is
+    /// `MatchStrategy::BinaryTree`. The output differs between strategies,
+    /// but either decompresses with any `Lzss` of the same parameters.
+    pub fn with_match_strategy(mut self, strategy: MatchStrategy) -> Self {
+        self.finder = match (strategy, self.max_chain) {
+            (MatchStrategy::BinaryTree, max_chain) => {
+                match_finder::tree_finder(self.n, max_chain.unwrap_or(usize::MAX), self.nearest_matches)
+            }
+            (MatchStrategy::HashChain, None) => Box::new(HashChainMatchFinder::new()),
+            (MatchStrategy::HashChain, Some(max_chain)) => Box::new(HashChainMatchFinder::with_max_chain(max_chain)),
+        };
+        self.strategy = Some(strategy);
+        self
     }
 
-    /// Initialize trees
-    fn init_tree(&mut self) {
-        // For i = 0 to N - 1, rson[i] and lson[i] will be the right and
-        // left children of node i.  These nodes need not be initialized.
-        // Also, dad[i] is the parent of node i.  These are initialized to
-        // NIL (= N), which stands for 'not used.'
-        // For i = 0 to 255, rson[N + i + 1] is the root of the tree
-        // for strings that begin with character i.  These are initialized
-        // to NIL.  Note there are 256 trees.
+    /// Limits the built-in match finder to comparing `max_chain` earlier
+    /// strings for each position, as a hard bound on the time spent per
+    /// byte. The trees have no limit by default, so adversarial input can
+    /// make them slow; with one they give up on a search that runs too
+    /// deep and take the best match found so far. Hash chains try 32 by
+    /// default. Either way only the ratio suffers, a little, and
+    /// decompression is unaffected.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// // Runs of zeros broken every 1000 bytes: the trees for 0 grow deep
+    /// let data: Vec<u8> = (0..100_000u32).map(|i| (i % 1000 == 999) as u8).collect();
+    /// let mut lzss = Lzss::with_params(16, 255)?.with_max_chain(64);
+    /// let compressed = lzss.compress(&data)?;
+    /// assert_eq!(lzss.decompress(&compressed)?, data);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn with_max_chain(mut self, max_chain: usize) -> Self {
+        self.max_chain = Some(max_chain);
+        match self.strategy {
+            Some(strategy) => self.with_match_strategy(strategy),
+            None => self,
+        }
+    }
 
-        for i in (Self::N + 1)..=(Self::N + 256) {
-            self.rson[i] = Self::NIL;
+    /// Among equally long matches, refers to the nearest one. The hash
+    /// chains always do, as they try the newest strings first; with this
+    /// the trees do too, as far as the strings they compare go. See
+    /// `BstMatchFinder::with_nearest_matches()`. Pairs are the same size
+    /// whatever their distance, so this changes the output's bytes, not
+    /// its length, and is off by default so that output matches LZSS.C's.
+    /// Decompression is unaffected.
+    ///
+    /// ```
+    /// use lzss::{Lzss, Token};
+    ///
+    /// // "abc" occurs 10 and 5 bytes before the last one
+    /// let data = b"abcdQabceRabcf";
+    /// let last_match = |tokens: Vec<Token>| tokens.into_iter().rev().find(|t| matches!(t, Token::Match { .. }));
+    /// let first = last_match(Lzss::new().tokenize(data)?);
+    /// let nearest = last_match(Lzss::new().with_nearest_matches(true).tokenize(data)?);
+    /// assert_eq!(first, Some(Token::Match { distance: 10, length: 3 }));
+    /// assert_eq!(nearest, Some(Token::Match { distance: 5, length: 3 }));
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn with_nearest_matches(mut self, nearest: bool) -> Self {
+        self.nearest_matches = nearest;
+        match self.strategy {
+            Some(strategy) => self.with_match_strategy(strategy),
+            None => self,
         }
-        for i in 0..Self::N {
-            self.dad[i] = Self::NIL;
+    }
+
+    /// Uses `finder` to find matches, in place of the built-in strategies.
+    /// See `MatchFinder` for what it has to provide.
+    pub fn with_match_finder<M: MatchFinder + 'static>(mut self, finder: M) -> Self {
+        self.finder = Box::new(finder);
+        self.strategy = None;
+        self
+    }
+
+    /// Like clone(), but None, rather than a panic, if a finder from
+    /// with_match_finder() can't be copied.
+    pub fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            n: self.n,
+            f: self.f,
+            window_bits: self.window_bits,
+            threshold: self.threshold,
+            lazy_matching: self.lazy_matching,
+            optimal_parsing: self.optimal_parsing,
+            flag_bits: self.flag_bits,
+            #[cfg(feature = "parallel")]
+            threads: self.threads,
+            fill: self.fill,
+            dictionary: self.dictionary.clone(),
+            text_buf: self.text_buf.clone(),
+            match_position: self.match_position,
+            match_length: self.match_length,
+            finder: self.finder.clone_box()?,
+            strategy: self.strategy,
+            max_chain: self.max_chain,
+            nearest_matches: self.nearest_matches,
+            enc: self.enc.clone(),
+            dec: self.dec.clone(),
+            decoding: self.decoding,
+        })
+    }
+
+    /// A new Lzss with the same settings, so other threads can produce the
+    /// same output. None if a custom MatchFinder is in use, since that can't
+    /// be copied.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn clone_settings(&self) -> Option<Self> {
+        let mut lzss = Self::with_params_and_threshold(self.window_bits, self.f, self.threshold)
+            .expect("parameters were already validated");
+        lzss.max_chain = self.max_chain;
+        lzss.nearest_matches = self.nearest_matches;
+        let mut lzss = lzss
+            .with_lazy_matching(self.lazy_matching)
+            .with_optimal_parsing(self.optimal_parsing)
+            .with_match_strategy(self.strategy?);
+        lzss.fill = self.fill;
+        lzss.flag_bits = self.flag_bits;
+        lzss.dictionary.clone_from(&self.dictionary);
+        Some(lzss)
+    }
+
+    /// Before sending a match, checks whether the string one byte further
+    /// on has a match at least two bytes longer, and if so sends a single
+    /// byte and takes that match instead. This usually compresses better at a small cost in
+    /// speed. Off by default, which gives the classic greedy output.
+    /// Decompression is unaffected.
+    pub fn with_lazy_matching(mut self, lazy: bool) -> Self {
+        self.lazy_matching = lazy;
+        self
+    }
+
+    /// Plans the whole parse before sending anything: a first pass records
+    /// the longest match at every position, and the units are then chosen
+    /// to make the output as small as those matches allow, which is never
+    /// larger than greedy or lazy matching. Takes precedence over
+    /// with_lazy_matching(). Decompression is unaffected.
+    ///
+    /// This is by far the slowest way to compress. It encodes the input
+    /// twice, weighs every match length at every position, and holds about
+    /// 12 bytes per input byte while it does. So it is off by default and
+    /// no level turns it on. It only applies where the whole input is at
+    /// hand, as in compress() and the other methods taking a slice;
+    /// compress_stream(), LzssEncoder and StepEncoder ignore it.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"abcdefgh bcdefghi abcdefghi abcdefghij".repeat(20);
+    /// let greedy = Lzss::new().compress(&data)?;
+    /// let optimal = Lzss::new().with_optimal_parsing(true).compress(&data)?;
+    /// assert!(optimal.len() <= greedy.len());
+    /// assert_eq!(Lzss::new().decompress(&optimal)?, data);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn with_optimal_parsing(mut self, optimal: bool) -> Self {
+        self.optimal_parsing = optimal;
+        self
+    }
+
+    /// Limits compress_parallel(), decompress_parallel() and
+    /// compress_framed_parallel() to at most `threads` threads, as on a
+    /// shared machine. 0, the default, means one per core. The output
+    /// doesn't depend on it.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// The limit set by with_threads(), or 0 for none.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Fills the ring buffer in front of the first string with `fill`
+    /// before each compression or decompression, in place of 0. Matches near the start of a stream may
+    /// refer to these bytes, so a byte common in the data, like `b' '` for
+    /// text as in LZSS.C, can shrink the first few units. The decompressor
+    /// must use the same fill byte; compress_framed() records it.
+    pub fn with_fill_byte(mut self, fill: u8) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Announces `bits` units with each flag word: 8, the default, as in
+    /// LZSS.C, or 16, in which case each code block starts with two flag
+    /// bytes, low byte first, and holds up to sixteen units. Either way a
+    /// unit costs one flag bit; only the last block of a stream, which
+    /// rarely fills its flags, and how often code is written out differ.
+    /// Anything else gives `LzssError::InvalidParams`. The decompressor
+    /// must use the same width; compress_framed() records it.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"sixteen units to a flag word, two bytes to a word".repeat(30);
+    /// let mut lzss = Lzss::new().with_flag_bits(16)?;
+    /// let compressed = lzss.compress(&data)?;
+    /// assert_eq!(lzss.decompress(&compressed)?, data);
+    /// assert_ne!(Lzss::new().decompress(&compressed).ok().as_ref(), Some(&data));
+    ///
+    /// // The framed format tells the decompressor
+    /// let framed = lzss.compress_framed(&data)?;
+    /// assert_eq!(Lzss::new().decompress_framed(&framed)?, data);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn with_flag_bits(mut self, bits: u32) -> Result<Self, LzssError> {
+        if bits != 8 && bits != 16 {
+            return Err(LzssError::InvalidParams);
         }
+        self.flag_bits = bits;
+        Ok(self)
     }
 
-    /// Inserts string of length F, text_buf[r..r+F-1], into one of the
-    /// trees (text_buf[r]'th tree) and returns the longest-match position
-    /// and length via the global variables match_position and match_length.
-    /// If match_length = F, then removes the old node in favor of the new
-    /// one, because the old one will be deleted sooner.
-    /// Note r plays double role, as tree node and position in buffer.
-    fn insert_node(&mut self, r: usize) {
-        let mut cmp = 1i32;
-        let key = r;
-        let mut p = Self::N + 1 + self.text_buf[key] as usize;
-        
-        self.rson[r] = Self::NIL;
-        self.lson[r] = Self::NIL;
+    /// Number of units each flag word announces.
+    pub(crate) fn flag_bits(&self) -> u32 {
+        self.flag_bits
+    }
+
+    pub(crate) fn set_flag_bits(&mut self, bits: u32) {
+        self.flag_bits = bits;
+    }
+
+    /// Number of bytes in a flag word
+    pub(crate) fn flag_bytes(&self) -> usize {
+        self.flag_bits as usize / 8
+    }
+
+    /// Splits the flag word off the start of a code block, or None if
+    /// `stream` ends inside it.
+    pub(crate) fn split_flags<'a>(&self, stream: &'a [u8]) -> Option<(u32, &'a [u8])> {
+        let (word, units) = stream.split_at_checked(self.flag_bytes())?;
+        Some((word.iter().rev().fold(0, |flags, &b| flags << 8 | b as u32), units))
+    }
+
+    /// The parameters a decompressor has to share: window bits, lookahead
+    /// and threshold.
+    pub(crate) fn params(&self) -> (u32, usize, usize) {
+        (self.window_bits, self.f, self.threshold)
+    }
+
+    /// The byte the ring buffer starts out filled with.
+    pub(crate) fn fill_byte(&self) -> u8 {
+        self.fill
+    }
+
+    pub(crate) fn set_fill_byte(&mut self, fill: u8) {
+        self.fill = fill;
+    }
+
+    /// Preloads the ring buffer with the end of `dictionary` before each
+    /// compression or decompression, so that even the first bytes of the
+    /// input can match what it contains. This pays off for many small
+    /// inputs with a lot in common, like messages of one JSON schema: put
+    /// their common parts in the dictionary, the most frequent last.
+    ///
+    /// Only the last N - F bytes, 2024 with the default parameters, are
+    /// used. Decompression needs the same dictionary, and the same
+    /// parameters, as compression. compress_framed() records a checksum of
+    /// it so that decompress_framed() can tell if it differs.
+    pub fn with_dictionary(mut self, dictionary: &[u8]) -> Self {
+        // The whole of it is kept, as far as any window can use it, for
+        // framed streams whose own parameters allow a longer one
+        let len = dictionary.len().min(1 << 20);
+        self.dictionary = dictionary[dictionary.len() - len..].to_vec();
+        self
+    }
+
+    /// The part of the preset dictionary in use, or an empty slice.
+    pub(crate) fn dictionary(&self) -> &[u8] {
+        &self.dictionary[self.dictionary.len().saturating_sub(self.n - self.f)..]
+    }
+
+    /// The preset dictionary as given to with_dictionary(), including what
+    /// this instance's window leaves out.
+    pub(crate) fn whole_dictionary(&self) -> &[u8] {
+        &self.dictionary
+    }
+
+    /// Discards everything left over from earlier compressions or
+    /// decompressions, keeping only the parameters. compress(), decompress()
+    /// and friends do this themselves, so back-to-back calls on one instance
+    /// are independent.
+    pub fn reset(&mut self) {
+        // The buffers are set up afresh by whichever side starts next, so
+        // only the progress needs forgetting.
+        self.match_position = 0;
         self.match_length = 0;
-        
+        self.enc = EncodeState::default();
+        self.dec = DecodeState { ring: core::mem::take(&mut self.dec.ring), ..DecodeState::default() };
+        self.decoding = false;
+    }
+
+    /// Fills a ring buffer as it is before the first byte of a stream. The
+    /// string about to be encoded or decoded starts at `end`, and only the
+    /// last `end` bytes of `dictionary` fit in front of it.
+    fn prime_ring(ring: &mut [u8], end: usize, fill: u8, dictionary: &[u8]) {
+        let dictionary = &dictionary[dictionary.len().saturating_sub(end)..];
+        // Clear the buffer with any character that will appear often. Only
+        // the N - F bytes in front of the first string can be matched before
+        // they are written; as in LZSS.C, the rest starts out zero. What it
+        // holds past the end of short input still decides which of several
+        // equally long matches the trees find.
+        ring[..end].fill(fill);
+        ring[end..].fill(0);
+        ring[end - dictionary.len()..end].copy_from_slice(dictionary);
+    }
+
+    /// Number of bytes in a position-and-length pair for a window of
+    /// `1 << window_bits` bytes
+    fn pair_len_for(window_bits: u32) -> usize {
+        if window_bits <= 15 { 2 } else { 3 }
+    }
+
+    /// Number of bytes in a position-and-length pair
+    pub(crate) fn pair_len(&self) -> usize {
+        Self::pair_len_for(self.window_bits)
+    }
+
+    /// Number of bits left for the length in a position-and-length pair
+    /// for a window of `1 << window_bits` bytes
+    fn length_bits_for(window_bits: u32) -> u32 {
+        Self::pair_len_for(window_bits) as u32 * 8 - window_bits
+    }
+
+    /// Longest lookahead with_params() accepts for a window of
+    /// `1 << window_bits` bytes
+    pub(crate) fn longest_lookahead(window_bits: u32) -> usize {
+        ((1usize << Self::length_bits_for(window_bits)) + Self::THRESHOLD).min((1usize << window_bits) / 2)
+    }
+
+    /// Number of bits left for the length in a position-and-length pair
+    fn length_bits(&self) -> u32 {
+        Self::length_bits_for(self.window_bits)
+    }
+
+    /// Registers the string at text_buf[r..r+F-1] with the match finder
+    /// and takes its longest match into match_position and match_length.
+    fn insert_node(&mut self, r: usize) {
+        // The finders read F bytes at r, and at positions before it, which
+        // the F - 1 bytes past the ring buffer keep inside text_buf.
+        debug_assert!(r < self.n);
+        debug_assert_eq!(self.text_buf.len(), self.n + self.f - 1);
+        self.finder.insert(r, &self.text_buf);
+        (self.match_position, self.match_length) = self.finder.best_match();
+        debug_assert!(self.match_length <= self.f);
+    }
+
+    /// Removes the string at p, which is about to be overwritten, from the
+    /// match finder.
+    fn delete_node(&mut self, p: usize) {
+        self.finder.remove(p);
+    }
+
+    #[cfg(feature = "std")]
+    fn encode<R: Read, W: Write>(&mut self, mut input: R, mut output: W) -> Result<(), LzssError> {
+        // Read in large chunks so throughput doesn't depend on the caller
+        // handing us a buffered reader.
+        let mut buffer = [0u8; 8192];
+
+        self.encode_begin();
         loop {
-            if cmp >= 0 {
-                if self.rson[p] != Self::NIL {
-                    p = self.rson[p];
-                } else {
-                    self.rson[p] = r;
-                    self.dad[r] = p;
-                    return;
-                }
-            } else {
-                if self.lson[p] != Self::NIL {
-                    p = self.lson[p];
-                } else {
-                    self.lson[p] = r;
-                    self.dad[r] = p;
-                    return;
-                }
+            match input.read(&mut buffer)? {
+                0 => break, // EOF
+                n => self.encode_update(&buffer[..n], &mut output)?,
             }
-            
-            let mut i = 1;
-            while i < Self::F {
-                cmp = self.text_buf[key + i] as i32 - self.text_buf[p + i] as i32;
-                if cmp != 0 {
-                    break;
+        }
+        self.encode_finish(&mut output)
+    }
+
+    /// Prepares the trees and buffers for a new encode_update() sequence.
+    pub(crate) fn encode_begin(&mut self) {
+        self.text_buf.resize(self.n + self.f - 1, 0);
+        Self::prime_ring(&mut self.text_buf, self.n - self.f, self.fill, &self.dictionary);
+        self.match_position = 0;
+        self.match_length = 0;
+        self.finder.reset(self.n, self.f); // initialize trees
+        self.decoding = false;
+
+        self.enc = EncodeState {
+            s: 0,
+            r: self.n - self.f,
+            history: self.dictionary().len(),
+            code_buf_ptr: self.flag_bytes(),
+            ..EncodeState::default()
+        };
+    }
+
+    /// Feeds more input to the encoder, writing out every code block that
+    /// fills up along the way.
+    pub(crate) fn encode_update<S: Sink>(&mut self, data: &[u8], output: &mut S) -> Result<(), LzssError> {
+        for &c in data {
+            if !self.enc.started {
+                // Read F bytes into the last F bytes of the buffer
+                self.text_buf[self.enc.r + self.enc.len] = c;
+                self.enc.len += 1;
+                if self.enc.len == self.f {
+                    self.encode_start(output)?;
                 }
-                i += 1;
+                continue;
             }
-            
-            if i > self.match_length {
-                self.match_position = p;
-                self.match_length = i;
-                if self.match_length >= Self::F {
-                    break;
-                }
+
+            let (s, r) = (self.enc.s, self.enc.r);
+            self.delete_node(s); // Delete old strings and
+            self.text_buf[s] = c; // read new bytes
+
+            if s < self.f - 1 {
+                self.text_buf[s + self.n] = c; // If the position is
+                                               // near the end of buffer, extend the buffer to make
+                                               // string comparison easier.
+            }
+
+            self.enc.s = (s + 1) & (self.n - 1);
+            self.enc.r = (r + 1) & (self.n - 1);
+            // Since this is a ring buffer, increment the position modulo N.
+            self.enc.history = (self.enc.history + 1).min(self.n - self.f);
+
+            self.enc.pos += 1;
+            self.insert_current(); // Register the string in text_buf[r..r+F-1]
+            self.enc.pending -= 1;
+            if self.enc.pending == 0 {
+                self.encode_unit(output)?;
             }
         }
-        
-        self.dad[r] = self.dad[p];
-        self.lson[r] = self.lson[p];
-        self.rson[r] = self.rson[p];
-        self.dad[self.lson[p]] = r;
-        self.dad[self.rson[p]] = r;
-        
-        if self.rson[self.dad[p]] == p {
-            self.rson[self.dad[p]] = r;
-        } else {
-            self.lson[self.dad[p]] = r;
-        }
-        
-        self.dad[p] = Self::NIL; // remove p
+
+        Ok(())
     }
 
-    /// Deletes node p from tree
-    fn delete_node(&mut self, p: usize) {
-        if self.dad[p] == Self::NIL {
-            return; // not in tree
-        }
-        
-        let q = if self.rson[p] == Self::NIL {
-            self.lson[p]
-        } else if self.lson[p] == Self::NIL {
-            self.rson[p]
-        } else {
-            let mut q = self.lson[p];
-            if self.rson[q] != Self::NIL {
-                while self.rson[q] != Self::NIL {
-                    q = self.rson[q];
-                }
-                self.rson[self.dad[q]] = self.lson[q];
-                self.dad[self.lson[q]] = self.dad[q];
-                self.lson[q] = self.lson[p];
-                self.dad[self.lson[p]] = q;
-            }
-            self.rson[q] = self.rson[p];
-            self.dad[self.rson[p]] = q;
-            q
-        };
-        
-        self.dad[q] = self.dad[p];
-        if self.rson[self.dad[p]] == p {
-            self.rson[self.dad[p]] = q;
-        } else {
-            self.lson[self.dad[p]] = q;
+    /// Encodes whatever input is left and writes out the final code block.
+    pub(crate) fn encode_finish<S: Sink>(&mut self, output: &mut S) -> Result<(), LzssError> {
+        while self.encode_tail_unit(output)? {}
+
+        let flag_bytes = self.flag_bytes();
+        if self.enc.code_buf_ptr > flag_bytes { // Send remaining code.
+            output.put(&self.enc.code_buf[..self.enc.code_buf_ptr])?;
+            if let Some(starts) = &mut self.enc.unit_starts {
+                starts.sent += self.enc.code_buf_ptr;
+            }
         }
-        self.dad[p] = Self::NIL;
-    }
+        self.enc.code_buf_ptr = flag_bytes;
 
-    fn encode<R: Read, W: Write>(&mut self, mut input: R, mut output: W) -> std::io::Result<()> {
-        let mut code_buf = [0u8; 17];
-        let mut mask: u8;
+        Ok(())
+    }
 
-        self.init_tree(); // initialize trees
-        
-        code_buf[0] = 0; // code_buf[1..16] saves eight units of code, and
-                        // code_buf[0] works as eight flags, "1" representing that the unit
-                        // is an unencoded letter (1 byte), "0" a position-and-length pair
-                        // (2 bytes). Thus, eight units require at most 16 bytes of code.
-        
-        let mut code_buf_ptr = 1;
-        mask = 1;
-        let s = 0;
-        let mut r = Self::N - Self::F;
-        
-        // Clear the buffer with any character that will appear often.
-        for i in s..r {
-            self.text_buf[i] = 0;
+    /// Once the input has ended, encodes the next unit of what is left.
+    /// Returns false, having sent nothing, when there is nothing left.
+    pub(crate) fn encode_tail_unit<S: Sink>(&mut self, output: &mut S) -> Result<bool, LzssError> {
+        if !self.enc.started {
+            if self.enc.len == 0 {
+                return Ok(false); // text of size zero
+            }
+            self.encode_start(output)?;
+            return Ok(true);
         }
-        
-        // Read F bytes into the last F bytes of the buffer
-        let mut len = 0;
-        let mut buffer = [0u8; 1];
-        while len < Self::F {
-            match input.read(&mut buffer)? {
-                0 => break, // EOF
-                _ => {
-                    self.text_buf[r + len] = buffer[0];
-                    len += 1;
-                }
+
+        while self.enc.pending > 0 { // After the end of text,
+            let s = self.enc.s;
+            self.delete_node(s); // no need to read, but
+            self.enc.s = (s + 1) & (self.n - 1);
+            self.enc.r = (self.enc.r + 1) & (self.n - 1);
+            self.enc.history = (self.enc.history + 1).min(self.n - self.f);
+            self.enc.pos += 1;
+            self.enc.len -= 1;
+            if self.enc.len != 0 {
+                self.insert_current(); // buffer may not be empty.
             }
+            self.enc.pending -= 1;
         }
-        
-        if len == 0 {
-            return Ok(()); // text of size zero
+
+        if self.enc.len == 0 {
+            return Ok(false); // until length of string to be processed is zero
         }
-        
+        self.encode_unit(output)?;
+        Ok(true)
+    }
+
+    /// Inserts the strings in front of the first F bytes and sends the first unit.
+    fn encode_start<S: Sink>(&mut self, output: &mut S) -> Result<(), LzssError> {
+        let r = self.enc.r;
+
+        // Strings of a preset dictionary older than the F below go in first,
+        // oldest first, so the F strings stay the newest.
+        for i in (self.f + 1..=self.dictionary().len()).rev() {
+            self.insert_node(r - i);
+        }
+
         // Insert the F strings, each of which begins with one or more 'space' characters.
         // Note the order in which these strings are inserted. This way,
         // degenerate trees will be less likely to occur.
-        for i in 1..=Self::F {
-            self.insert_node(r.wrapping_sub(i));
+        // with_params() keeps F at most N / 2, so r = N - F is at least F
+        // and none of these positions is before the start of text_buf.
+        for i in 1..=self.f {
+            self.insert_node(r - i);
         }
-        
+
         // Finally, insert the whole string just read. The
         // global variables match_length and match_position are set.
-        self.insert_node(r);
-        
-        let mut s = s;
-        
-        loop {
-            if self.match_length > len {
-                self.match_length = len; // match_length may be spuriously long near the end of text.
-            }
-            
-            if self.match_length <= Self::THRESHOLD {
-                self.match_length = 1; // Not long enough match. Send one byte.
-                code_buf[0] |= mask; // 'send one byte' flag
-                code_buf[code_buf_ptr] = self.text_buf[r]; // Send uncoded.
-                code_buf_ptr += 1;
+        self.insert_current();
+
+        self.enc.started = true;
+        self.encode_unit(output)
+    }
+
+    /// Sends the unit at r, as found by the last insert_node(), and notes how
+    /// many bytes must be read before the next one.
+    fn encode_unit<S: Sink>(&mut self, output: &mut S) -> Result<(), LzssError> {
+        // match_length may be spuriously long near the end of text, where
+        // the finder compares F bytes but fewer than F are left and the rest
+        // of the lookahead is stale. A match agrees with the text at r over
+        // its whole length, so clipping it to the bytes that are left still
+        // leaves one that agrees with the input; send_pair() checks this in
+        // debug builds.
+        if self.match_length > self.enc.len {
+            self.match_length = self.enc.len;
+        }
+
+        if let Some(starts) = &mut self.enc.unit_starts {
+            // A unit that opens a code block is found by its flags
+            let offset = match (self.enc.flag_byte, self.enc.mask) {
+                (0, 1) => starts.sent,
+                _ => starts.sent + self.enc.code_buf_ptr,
+            };
+            while starts.pending.last() == Some(&self.enc.pos) {
+                starts.pending.pop();
+                starts.offsets.push(offset);
+            }
+            // Stop short of the next position, so that a unit starts there.
+            // A match held back by lazy matching was cut short when found.
+            if let Some(&next) = starts.pending.last() {
+                self.match_length = self.match_length.min(next - self.enc.pos);
+            }
+        }
+
+        if let Some(plan) = &self.enc.plan {
+            // The plan only ever asks for a prefix of the match found here.
+            let length = plan[self.enc.pos] as usize;
+            if length <= self.threshold {
+                self.send_literal(self.text_buf[self.enc.r], output)?;
             } else {
-                code_buf[code_buf_ptr] = self.match_position as u8;
-                code_buf_ptr += 1;
-                code_buf[code_buf_ptr] = (((self.match_position >> 3) & 0xe0) | 
-                                        (self.match_length - (Self::THRESHOLD + 1))) as u8;
-                code_buf_ptr += 1;
-            }
-            
-            mask <<= 1;
-            if mask == 0 { // Shift mask left one bit.
-                // Send at most 8 units of code together
-                for i in 0..code_buf_ptr {
-                    output.write_all(&[code_buf[i]])?;
-                }
-                code_buf[0] = 0;
-                code_buf_ptr = 1;
-                mask = 1;
-            }
-            
-            let last_match_length = self.match_length;
-            let mut i = 0;
-            
-            while i < last_match_length {
-                match input.read(&mut buffer)? {
-                    0 => break, // EOF
-                    _ => {
-                        self.delete_node(s); // Delete old strings and
-                        self.text_buf[s] = buffer[0]; // read new bytes
-                        
-                        if s < Self::F - 1 {
-                            self.text_buf[s + Self::N] = buffer[0]; // If the position is
-                                                                   // near the end of buffer, extend the buffer to make
-                                                                   // string comparison easier.
-                        }
-                        
-                        s = (s + 1) & (Self::N - 1);
-                        r = (r + 1) & (Self::N - 1);
-                        // Since this is a ring buffer, increment the position modulo N.
-                        
-                        self.insert_node(r); // Register the string in text_buf[r..r+F-1]
-                        i += 1;
-                    }
-                }
+                self.send_pair(self.enc.r, self.match_position, length, output)?;
             }
-            
-            while i < last_match_length { // After the end of text,
-                self.delete_node(s); // no need to read, but
-                s = (s + 1) & (Self::N - 1);
-                r = (r + 1) & (Self::N - 1);
-                len -= 1;
-                if len != 0 {
-                    self.insert_node(r); // buffer may not be empty.
-                }
-                i += 1;
+            self.enc.pending = length;
+            return Ok(());
+        }
+
+        if let Some((position, length)) = self.enc.deferred.take() {
+            // Sending r - 1 uncoded costs a byte, which a match only one
+            // longer doesn't earn back.
+            if self.match_length <= length + 1 {
+                // Send the match held back at r - 1 and skip what it covers
+                // past r - 1.
+                let r = (self.enc.r + self.n - 1) & (self.n - 1);
+                self.send_pair(r, position, length, output)?;
+                self.enc.pending = length - 1;
+                return Ok(());
             }
-            
-            if len == 0 {
-                break; // until length of string to be processed is zero
+            // A longer match starts here, so r - 1 goes out uncoded.
+            let c = self.text_buf[(self.enc.r + self.n - 1) & (self.n - 1)];
+            self.send_literal(c, output)?;
+        }
+
+        if self.match_length <= self.threshold {
+            self.match_length = 1; // Not long enough match. Send one byte.
+            self.send_literal(self.text_buf[self.enc.r], output)?;
+        } else if self.lazy_matching && self.match_length < self.f {
+            // Hold the match back until the one at r + 1 is known.
+            debug_assert!(self.is_match(self.enc.r, self.match_position, self.match_length));
+            self.enc.deferred = Some((self.match_position, self.match_length));
+            self.match_length = 1;
+        } else {
+            self.send_pair(self.enc.r, self.match_position, self.match_length, output)?;
+        }
+
+        self.enc.pending = self.match_length;
+        Ok(())
+    }
+
+    /// Registers the string at r, noting the longest match found for it
+    /// when planning a parse.
+    fn insert_current(&mut self) {
+        self.insert_node(self.enc.r);
+        if let Some(longest) = &mut self.enc.longest {
+            longest.push(self.match_length as u16);
+        }
+    }
+
+    /// Makes the encoder start a unit at each of `positions` in the input,
+    /// which must be in ascending order, so no match spans one, for
+    /// take_unit_offsets().
+    pub(crate) fn start_units_at(&mut self, positions: &[usize]) {
+        self.enc.unit_starts = Some(UnitStarts {
+            pending: positions.iter().rev().copied().collect(),
+            ..UnitStarts::default()
+        });
+    }
+
+    /// The offset in the code of the unit at each position given to
+    /// start_units_at(), once encode_finish() has sent the last code
+    /// block. Positions at the end of the input get the length of the code.
+    pub(crate) fn take_unit_offsets(&mut self) -> Vec<usize> {
+        let Some(starts) = self.enc.unit_starts.take() else {
+            return Vec::new();
+        };
+        let mut offsets = starts.offsets;
+        offsets.extend(starts.pending.iter().map(|_| starts.sent));
+        offsets
+    }
+
+    /// Makes the encoder keep every unit it sends, for take_tokens().
+    pub(crate) fn record_tokens(&mut self) {
+        self.enc.tokens = Some(Vec::new());
+    }
+
+    /// The units sent since the last call, if record_tokens() was called.
+    pub(crate) fn take_tokens(&mut self) -> Vec<Token> {
+        self.enc.tokens.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    /// The longest match the match finder reported for the string at r,
+    /// as a token, or None if it is no longer than the threshold.
+    pub(crate) fn current_match(&self) -> Option<Token> {
+        if !self.enc.started {
+            return None;
+        }
+        let (position, length) = self.finder.best_match();
+        let length = length.min(self.enc.len);
+        if length <= self.threshold {
+            return None;
+        }
+        let distance = match (self.enc.r + self.n - position) & (self.n - 1) {
+            0 => self.n,
+            d => d,
+        };
+        Some(Token::Match { distance, length })
+    }
+
+    /// Adds an unencoded letter to code_buf.
+    fn send_literal<S: Sink>(&mut self, c: u8, output: &mut S) -> Result<(), LzssError> {
+        let enc = &mut self.enc;
+        enc.code_buf[enc.flag_byte] |= enc.mask; // 'send one byte' flag
+        enc.code_buf[enc.code_buf_ptr] = c; // Send uncoded.
+        enc.code_buf_ptr += 1;
+        if let Some(stats) = &mut enc.stats {
+            stats.literals += 1;
+        }
+        if let Some(tokens) = &mut enc.tokens {
+            tokens.push(Token::Literal(c));
+        }
+        self.next_unit(output)
+    }
+
+    /// Whether the `length` bytes at `position` in the ring buffer are those
+    /// at r, as they must be for a pair to decode to the input.
+    fn is_match(&self, r: usize, position: usize, length: usize) -> bool {
+        (0..length).all(|i| self.text_buf[(position + i) & (self.n - 1)] == self.text_buf[(r + i) & (self.n - 1)])
+    }
+
+    /// Adds a position-and-length pair for the string at r to code_buf.
+    fn send_pair<S: Sink>(&mut self, r: usize, position: usize, length: usize, output: &mut S) -> Result<(), LzssError> {
+        let length_bits = self.length_bits();
+        let high_shift = 8 * (self.pair_len() - 1);
+        // The length field holds length - (THRESHOLD + 1), so the longest
+        // match, F, must still fit in it. with_params_and_threshold() and
+        // with_threshold() guarantee this.
+        let threshold = self.threshold;
+        debug_assert!(length > threshold && length <= self.f);
+        debug_assert!(length - (threshold + 1) < 1 << length_bits);
+        // A pair held back by lazy matching was checked when it was found.
+        // The byte read since may have overwritten the start of the oldest
+        // string, which the decoder still has as it was.
+        debug_assert!(r != self.enc.r || self.is_match(r, position, length), "pair at {} doesn't match {} bytes at {}", r, length, position);
+        let enc = &mut self.enc;
+        for shift in (0..high_shift).step_by(8) {
+            enc.code_buf[enc.code_buf_ptr] = (position >> shift) as u8;
+            enc.code_buf_ptr += 1;
+        }
+        enc.code_buf[enc.code_buf_ptr] = (((position >> high_shift) << length_bits) |
+                                         (length - (threshold + 1))) as u8;
+        enc.code_buf_ptr += 1;
+        if let Some(stats) = &mut enc.stats {
+            stats.matches += 1;
+            stats.matched_bytes += length as u64;
+            stats.longest_match = stats.longest_match.max(length);
+            if stats.match_length_histogram.len() <= length {
+                stats.match_length_histogram.resize(length + 1, 0);
             }
+            stats.match_length_histogram[length] += 1;
         }
-        
-        if code_buf_ptr > 1 { // Send remaining code.
-            for i in 0..code_buf_ptr {
-                output.write_all(&[code_buf[i]])?;
+        if let Some(tokens) = &mut enc.tokens {
+            // Measured the way the decoder does, where 0 means a full N back.
+            let distance = match (r + self.n - position) & (self.n - 1) {
+                0 => self.n,
+                d => d,
+            };
+            tokens.push(Token::Match { distance, length });
+        }
+        self.next_unit(output)
+    }
+
+    /// Moves to the next flag bit, sending code_buf once all of them are used.
+    fn next_unit<S: Sink>(&mut self, output: &mut S) -> Result<(), LzssError> {
+        let flag_bytes = self.flag_bytes();
+        let enc = &mut self.enc;
+        enc.mask <<= 1;
+        if enc.mask == 0 { // Shift mask left one bit.
+            enc.mask = 1;
+            enc.flag_byte += 1;
+            if enc.flag_byte == flag_bytes {
+                // Send at most 8 or 16 units of code together
+                output.put(&enc.code_buf[..enc.code_buf_ptr])?;
+                if let Some(starts) = &mut enc.unit_starts {
+                    starts.sent += enc.code_buf_ptr;
+                }
+                enc.code_buf[..flag_bytes].fill(0);
+                enc.code_buf_ptr = flag_bytes;
+                enc.flag_byte = 0;
             }
         }
-        
         Ok(())
     }
 
     /// Just the reverse of encode()
-    fn decode<R: Read, W: Write>(&mut self, mut input: R, mut output: W) -> std::io::Result<()> {
-        for i in 0..(Self::N - Self::F) {
-            self.text_buf[i] = 0;
-        }
-        
-        let mut r = Self::N - Self::F;
-        let mut flags = 0u32;
-        let mut buffer = [0u8; 1];
-        
+    #[cfg(feature = "std")]
+    fn decode<R: Read, W: Write>(&mut self, mut input: R, mut output: W) -> Result<(), LzssError> {
+        let mut buffer = [0u8; 8192];
+
+        self.decode_begin();
         loop {
-            flags >>= 1;
-            if (flags & 256) == 0 {
-                match input.read(&mut buffer)? {
-                    0 => break, // EOF
-                    _ => {
-                        flags = (buffer[0] as u32) | 0xff00; // uses higher byte cleverly to count eight
-                    }
-                }
+            match input.read(&mut buffer)? {
+                0 => break, // EOF
+                n => self.decode_update(&buffer[..n], &mut output)?,
             }
-            
-            if (flags & 1) != 0 {
-                match input.read(&mut buffer)? {
-                    0 => break, // EOF
-                    _ => {
-                        output.write_all(&[buffer[0]])?;
-                        self.text_buf[r] = buffer[0];
-                        r += 1;
-                        r &= Self::N - 1;
-                    }
+        }
+        self.decode_finish()
+    }
+
+    /// Prepares the ring buffer for a new decode_update() sequence.
+    pub(crate) fn decode_begin(&mut self) {
+        let mut ring = core::mem::take(&mut self.dec.ring);
+        ring.resize(self.n, 0);
+        Self::prime_ring(&mut ring, self.n - self.f, self.fill, &self.dictionary);
+        self.decoding = true;
+
+        self.dec = DecodeState {
+            ring,
+            r: self.n - self.f,
+            populated: self.f.max(self.dictionary().len()),
+            history: self.dictionary().len(),
+            ..DecodeState::default()
+        };
+    }
+
+    /// The decoder's ring buffer, which together with the number of bytes
+    /// decoded so far is all it carries from one code block to the next.
+    pub(crate) fn ring(&self) -> &[u8] {
+        &self.dec.ring
+    }
+
+    /// Prepares the decoder to carry on at a code block boundary, `position`
+    /// bytes into the decompressed data, with `ring` as saved by ring() at
+    /// that point.
+    pub(crate) fn decode_resume(&mut self, ring: &[u8], position: u64) {
+        self.decode_begin();
+        self.dec.ring.copy_from_slice(ring);
+        self.dec.r = (self.dec.r + (position % self.n as u64) as usize) & (self.n - 1);
+        self.dec.populated = (self.dec.populated as u64).saturating_add(position).min(self.n as u64) as usize;
+        self.dec.history = (self.dec.history as u64).saturating_add(position).min(self.n as u64) as usize;
+    }
+
+    /// Feeds more compressed input to the decoder. Units may be split across
+    /// calls at any byte.
+    pub(crate) fn decode_update<S: Sink>(&mut self, data: &[u8], output: &mut S) -> Result<(), LzssError> {
+        for &c in data {
+            if self.flag_bits == 16 && (self.dec.flags & 1 << 16) == 0 {
+                // Low byte first, then the higher 16 bits count sixteen
+                if self.dec.flag_bytes == 0 {
+                    self.dec.flags = c as u32;
+                    self.dec.flag_bytes = 1;
+                } else {
+                    self.dec.flags |= (c as u32) << 8 | 0xffff0000;
+                    self.dec.flag_bytes = 0;
+                    self.dec.fresh_flags = true;
                 }
+                continue;
+            }
+            if self.flag_bits == 8 && (self.dec.flags & 256) == 0 {
+                self.dec.flags = (c as u32) | 0xff00; // uses higher byte cleverly to count eight
+                self.dec.fresh_flags = true;
+                continue;
+            }
+            self.dec.fresh_flags = false;
+
+            if (self.dec.flags & 1) != 0 {
+                output.put(&[c])?;
+                let r = self.dec.r;
+                self.dec.ring[r] = c;
+                self.dec.r = (r + 1) & (self.n - 1);
+                self.dec.populated = (self.dec.populated + 1).min(self.n);
+                self.dec.history = (self.dec.history + 1).min(self.n);
             } else {
-                let i = match input.read(&mut buffer)? {
-                    0 => break, // EOF
-                    _ => buffer[0] as usize,
-                };
-                
-                let j = match input.read(&mut buffer)? {
-                    0 => break, // EOF
-                    _ => buffer[0] as usize,
+                let high_shift = 8 * (self.pair_len() - 1);
+                if self.dec.pair_filled * 8 < high_shift {
+                    self.dec.pair[self.dec.pair_filled] = c; // wait for the last byte of the pair
+                    self.dec.pair_filled += 1;
+                    continue;
+                }
+                let i = self.dec.pair[..self.dec.pair_filled]
+                    .iter()
+                    .rev()
+                    .fold(0, |i, &b| i << 8 | b as usize);
+                self.dec.pair_filled = 0;
+                let j = c as usize;
+
+                let pos = i | ((j >> self.length_bits()) << high_shift);
+                // The pair encodes match_length - (THRESHOLD + 1), so the copy
+                // below of 0..=length is exactly match_length bytes, and never
+                // fewer than two: no unit can fail to make progress.
+                let length = (j & ((1 << self.length_bits()) - 1)) + self.threshold;
+
+                let distance = match (self.dec.r + self.n - pos) & (self.n - 1) {
+                    0 => self.n,
+                    d => d,
                 };
-                
-                let pos = i | ((j & 0xe0) << 3);
-                let length = (j & 0x1f) + Self::THRESHOLD;
-                
+                if distance > self.dec.populated {
+                    return Err(LzssError::InvalidBackreference { pos, len: length + 1 });
+                }
+                self.dec.populated = (self.dec.populated + length + 1).min(self.n);
+                self.dec.history = (self.dec.history + length + 1).min(self.n);
+
+                self.dec.unit.clear();
+                let mut r = self.dec.r;
+                // Both indexes stay masked to the ring buffer, so no length
+                // the pair can express reaches past it.
                 for k in 0..=length {
-                    let c = self.text_buf[(pos + k) & (Self::N - 1)];
-                    output.write_all(&[c])?;
-                    self.text_buf[r] = c;
+                    let c = self.dec.ring[(pos + k) & (self.n - 1)];
+                    self.dec.unit.push(c);
+                    self.dec.ring[r] = c;
                     r += 1;
-                    r &= Self::N - 1;
+                    r &= self.n - 1;
                 }
+                self.dec.r = r;
+                output.put(&self.dec.unit)?;
             }
+            self.dec.flags >>= 1;
         }
-        
+
         Ok(())
     }
 
-    pub fn compress(&mut self, buffer: &[u8]) -> std::io::Result<Vec<u8>> {
-        let input = std::io::Cursor::new(buffer);
-        let mut output = Vec::new();
-        
-        self.encode(input, &mut output)?;
-        
-        Ok(output)
+    /// A copy of what the ring buffer holds behind the current position,
+    /// oldest first: the dictionary and the data encoded or decoded so far,
+    /// but not the fill byte the buffer starts out with. Once the data
+    /// outgrows the window this is its last N - F bytes while compressing,
+    /// or its last N bytes while decompressing, whichever was begun last.
+    /// Meant for looking inside, say to show how the window evolves;
+    /// nothing else depends on it.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let mut lzss = Lzss::new();
+    /// lzss.compress(b"to be or not to be")?;
+    /// assert_eq!(lzss.window_snapshot(), b"to be or not to be");
+    ///
+    /// let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+    /// let compressed = lzss.compress(&data)?;
+    /// assert_eq!(lzss.window_snapshot(), &data[5000 - 2024..]);
+    /// lzss.decompress(&compressed)?;
+    /// assert_eq!(lzss.window_snapshot(), &data[5000 - 2048..]);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn window_snapshot(&self) -> Vec<u8> {
+        let (ring, r, history) = if self.decoding {
+            (&self.dec.ring, self.dec.r, self.dec.history)
+        } else {
+            (&self.text_buf, self.enc.r, self.enc.history)
+        };
+        (r + self.n - history..r + self.n)
+            .map(|i| ring[i & (self.n - 1)])
+            .collect()
     }
 
-    pub fn decompress(&mut self, buffer: &[u8]) -> std::io::Result<Vec<u8>> {
-        let input = std::io::Cursor::new(buffer);
-        let mut output = Vec::new();
-        
-        self.decode(input, &mut output)?;
-        
+    /// Checks that the compressed input did not stop in the middle of a unit.
+    pub(crate) fn decode_finish(&mut self) -> Result<(), LzssError> {
+        // A clean stream ends on a unit boundary, with at least one unit
+        // after the last flags byte.
+        if self.dec.fresh_flags || self.dec.flag_bytes > 0 || self.dec.pair_filled > 0 {
+            return Err(LzssError::TruncatedStream);
+        }
+
+        Ok(())
+    }
+
+    /// Runs the whole of `buffer` through the encoder into `output`.
+    pub(crate) fn encode_slice<S: Sink>(&mut self, buffer: &[u8], output: &mut S) -> Result<(), LzssError> {
+        self.encode_slice_with(buffer, output, |_| {}, None)
+    }
+
+    /// Like encode_slice(), with `record` setting up what the encoder should
+    /// keep track of on the way. With optimal parsing on, first plans the
+    /// units from a pass that only notes the matches.
+    /// With `cancel`, gives up with `LzssError::Cancelled` once it is set.
+    fn encode_slice_with<S: Sink>(&mut self, buffer: &[u8], output: &mut S, record: fn(&mut EncodeState), cancel: Option<&AtomicBool>) -> Result<(), LzssError> {
+        let plan = if self.optimal_parsing {
+            self.encode_begin();
+            self.enc.longest = Some(Vec::with_capacity(buffer.len()));
+            self.encode_update_checked(buffer, &mut CountingSink::default(), cancel)?;
+            self.encode_finish(&mut CountingSink::default())?;
+            let longest = self.enc.longest.take().unwrap_or_default();
+            Some(optimal::plan(&longest, self.threshold, self.pair_len()))
+        } else {
+            None
+        };
+
+        self.encode_begin();
+        self.enc.plan = plan;
+        record(&mut self.enc);
+        self.encode_update_checked(buffer, output, cancel)?;
+        self.encode_finish(output)?;
+        self.enc.plan = None;
+        Ok(())
+    }
+
+    /// Feeds `buffer` to the encoder a piece at a time, checking `cancel`,
+    /// if any, before each piece.
+    fn encode_update_checked<S: Sink>(&mut self, buffer: &[u8], output: &mut S, cancel: Option<&AtomicBool>) -> Result<(), LzssError> {
+        let Some(cancel) = cancel else {
+            return self.encode_update(buffer, output);
+        };
+        // A relaxed load per few KB is lost in the cost of encoding them
+        for piece in buffer.chunks(Self::CANCEL_CHECK_INTERVAL) {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(LzssError::Cancelled);
+            }
+            self.encode_update(piece, output)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the whole of `buffer` through the decoder into `output`.
+    pub(crate) fn decode_slice<S: Sink>(&mut self, buffer: &[u8], output: &mut S) -> Result<(), LzssError> {
+        self.decode_begin();
+        self.decode_update(buffer, output)?;
+        self.decode_finish()
+    }
+
+    /// Compresses `buffer`. Empty input compresses to an empty stream; there
+    /// is no header or end marker in this format.
+    pub fn compress(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        
+        self.encode_slice(buffer, &mut output)?;
+        
+        Ok(output)
+    }
+
+    /// Like compress(), but reserves `capacity` bytes of output up front.
+    /// With a good guess at the compressed size, say from the ratio of
+    /// similar data, the output is allocated once rather than grown as it
+    /// fills. The output is the same either way.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"a guess at the size saves the copying ".repeat(100);
+    /// let compressed = Lzss::new().compress_with_capacity(&data, data.len() / 4)?;
+    /// assert_eq!(compressed, Lzss::new().compress(&data)?);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_with_capacity(&mut self, buffer: &[u8], capacity: usize) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::with_capacity(capacity);
+
+        self.encode_slice(buffer, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Like compress(), but also reports how the input was encoded.
+    pub fn compress_with_stats(&mut self, buffer: &[u8]) -> Result<(Vec<u8>, CompressionStats), LzssError> {
+        let mut output = Vec::new();
+
+        self.encode_slice_with(buffer, &mut output, |enc| enc.stats = Some(CompressionStats::default()), None)?;
+
+        let mut stats = self.enc.stats.take().unwrap_or_default();
+        stats.expanded = output.len() > buffer.len();
+        Ok((output, stats))
+    }
+
+    /// Like compress(), but also returns every unit sent, in order. Handy
+    /// for seeing why some input compresses poorly.
+    ///
+    /// ```
+    /// use lzss::{Lzss, Token};
+    ///
+    /// let (_, tokens) = Lzss::new().compress_with_tokens(b"abcabcabcd")?;
+    /// assert_eq!(tokens, [
+    ///     Token::Literal(b'a'),
+    ///     Token::Literal(b'b'),
+    ///     Token::Literal(b'c'),
+    ///     Token::Match { distance: 3, length: 6 },
+    ///     Token::Literal(b'd'),
+    /// ]);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_with_tokens(&mut self, buffer: &[u8]) -> Result<(Vec<u8>, Vec<Token>), LzssError> {
+        let mut output = Vec::new();
+
+        self.encode_slice_with(buffer, &mut output, |enc| enc.tokens = Some(Vec::new()), None)?;
+
+        Ok((output, self.enc.tokens.take().unwrap_or_default()))
+    }
+
+    /// Runs the encoder over `buffer` and returns the units it would send,
+    /// without producing the compressed bytes. Together with detokenize()
+    /// this allows wrapping the matches in a format of one's own.
+    pub fn tokenize(&mut self, buffer: &[u8]) -> Result<Vec<Token>, LzssError> {
+        let mut output = CountingSink::default();
+
+        self.encode_slice_with(buffer, &mut output, |enc| enc.tokens = Some(Vec::new()), None)?;
+
+        Ok(self.enc.tokens.take().unwrap_or_default())
+    }
+
+    /// Reverse of tokenize(). Tokens are held to what a compressed stream
+    /// could contain: a match must be longer than the threshold and fit the
+    /// length field, and may reach at most N bytes back and, as the ring
+    /// buffer starts out with F zero bytes, at most F bytes before the start.
+    /// Anything else fails with `LzssError::InvalidBackreference`.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let mut lzss = Lzss::new();
+    /// let tokens = lzss.tokenize(b"to be or not to be")?;
+    /// assert_eq!(lzss.detokenize(&tokens)?, b"to be or not to be");
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn detokenize(&self, tokens: &[Token]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        let dictionary = self.dictionary();
+
+        for &token in tokens {
+            match token {
+                Token::Literal(c) => output.push(c),
+                Token::Match { distance, length } => {
+                    let populated = (output.len() + self.f.max(dictionary.len())).min(self.n);
+                    let max_length = (1 << self.length_bits()) + self.threshold;
+                    if distance == 0 || distance > populated || length <= self.threshold || length > max_length {
+                        return Err(LzssError::InvalidBackreference { pos: output.len(), len: length });
+                    }
+                    for _ in 0..length {
+                        // Bytes before the start are the dictionary, and before
+                        // that the fill, that text_buf begins with.
+                        let c = match output.len().checked_sub(distance) {
+                            Some(i) => output[i],
+                            None => {
+                                let back = distance - output.len();
+                                dictionary.len().checked_sub(back).map_or(self.fill, |i| dictionary[i])
+                            }
+                        };
+                        output.push(c);
+                    }
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Compresses `src` into the start of `dst` and returns the number of
+    /// bytes written, or `LzssError::BufferTooSmall` if they don't fit.
+    pub fn compress_into(&mut self, src: &[u8], dst: &mut [u8]) -> Result<usize, LzssError> {
+        let mut output = SliceSink::new(dst);
+
+        self.encode_slice(src, &mut output)?;
+
+        Ok(output.len())
+    }
+
+    /// Compresses `s` into `out`, replacing what it held but keeping its
+    /// allocation, so compressing many short strings, such as log lines,
+    /// through one `Vec` doesn't allocate for each:
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let mut lzss = Lzss::new();
+    /// let mut out = Vec::new();
+    /// for line in ["GET /index.html 200", "GET /favicon.ico 404", ""] {
+    ///     lzss.compress_str(line, &mut out)?;
+    ///     assert_eq!(lzss.decompress(&out)?, line.as_bytes());
+    /// }
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_str(&mut self, s: &str, out: &mut Vec<u8>) -> Result<(), LzssError> {
+        out.clear();
+
+        self.encode_slice(s.as_bytes(), out)
+    }
+
+    /// Compresses `input` as the next part of a stream whose data arrives
+    /// in pieces, appending what can already be sent to `out`. The first
+    /// call starts a new stream; compress_finish() ends it. Code is written
+    /// a block of eight units at a time, or sixteen with with_flag_bits(),
+    /// and up to F bytes of input are held back for matching, so `out` may
+    /// trail the input a little until then. LzssWriter does the same
+    /// through `Write`. However the input is split up, the output is that of
+    /// compress() on all of it, apart from optimal parsing, which needs the
+    /// whole input and is ignored.
+    ///
+    /// Decompressing with this instance in between leaves the stream as
+    /// it was, since the decoder keeps state of its own. Any other
+    /// compression in between starts over, dropping the unfinished stream.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"a message, then another message, then one more message".repeat(20);
+    /// let mut lzss = Lzss::new();
+    /// let mut out = Vec::new();
+    /// for piece in data.chunks(7) {
+    ///     lzss.compress_update(piece, &mut out)?;
+    /// }
+    /// lzss.compress_finish(&mut out)?;
+    /// assert_eq!(out, lzss.compress(&data)?);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_update(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), LzssError> {
+        if !self.enc.incremental {
+            self.encode_begin();
+            self.enc.incremental = true;
+        }
+
+        self.encode_update(input, out)
+    }
+
+    /// Ends a stream begun by compress_update(), appending the rest of its
+    /// code to `out`. Without a compress_update() before, this is an empty
+    /// stream, which has no code.
+    pub fn compress_finish(&mut self, out: &mut Vec<u8>) -> Result<(), LzssError> {
+        if !self.enc.incremental {
+            self.encode_begin();
+        }
+        self.enc.incremental = false;
+
+        self.encode_finish(out)
+    }
+
+    /// Like compress(), but gives up with `LzssError::Cancelled` soon after
+    /// `cancel` is set, as from another thread. The flag is checked every
+    /// few KB of input.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use lzss::{Lzss, LzssError};
+    ///
+    /// let cancel = AtomicBool::new(false);
+    /// let data = b"a long job".repeat(100_000);
+    /// std::thread::scope(|scope| {
+    ///     let job = scope.spawn(|| Lzss::with_level(9)?.compress_cancellable(&data, &cancel));
+    ///     cancel.store(true, Ordering::Relaxed);
+    ///     assert!(matches!(job.join().unwrap(), Err(LzssError::Cancelled)));
+    /// });
+    /// # Ok::<(), LzssError>(())
+    /// ```
+    pub fn compress_cancellable(&mut self, buffer: &[u8], cancel: &AtomicBool) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+
+        self.encode_slice_with(buffer, &mut output, |_| {}, Some(cancel))?;
+
+        Ok(output)
+    }
+
+    /// Number of bytes compress() would produce for `buffer`, found without
+    /// keeping the compressed output.
+    pub fn compressed_size(&mut self, buffer: &[u8]) -> Result<u64, LzssError> {
+        let mut output = CountingSink::default();
+
+        self.encode_slice(buffer, &mut output)?;
+
+        Ok(output.len())
+    }
+
+    /// Compressed size of `buffer` as a fraction of its original size, so
+    /// below 1.0 means it shrinks. Empty input counts as 1.0.
+    pub fn compress_ratio(&mut self, buffer: &[u8]) -> Result<f64, LzssError> {
+        let compressed_size = self.compressed_size(buffer)?;
+
+        Ok(CompressionSummary::new(buffer.len() as u64, compressed_size).ratio())
+    }
+
+    /// Compresses everything `input` yields into `output` without buffering
+    /// the whole input in memory. This is the encoder itself, which
+    /// compress() runs over a slice: input is read in 8 KB pieces, and each
+    /// block of up to eight or sixteen units is written as soon as it is complete, so
+    /// a `BufWriter` helps when `output` is a file or socket. Any reader
+    /// and writer will do, as here a pair of pipes:
+    ///
+    /// ```
+    /// use std::io::{self, Read, Write};
+    /// use std::thread;
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"through one pipe and out of another ".repeat(500);
+    /// let (mut plain_reader, mut plain_writer) = io::pipe()?;
+    /// let (mut packed_reader, packed_writer) = io::pipe()?;
+    ///
+    /// let feeder = thread::spawn({
+    ///     let data = data.clone();
+    ///     move || plain_writer.write_all(&data)
+    /// });
+    /// let compressor = thread::spawn(move || Lzss::new().compress_stream(&mut plain_reader, packed_writer));
+    /// let mut decompressed = Vec::new();
+    /// Lzss::new().decompress_stream(&mut packed_reader, &mut decompressed)?;
+    ///
+    /// feeder.join().unwrap()?;
+    /// compressor.join().unwrap()?;
+    /// assert_eq!(decompressed, data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn compress_stream<R: Read, W: Write>(&mut self, input: R, output: W) -> Result<(), LzssError> {
+        self.encode(input, output)
+    }
+
+    /// Reverse of compress(). An empty stream decompresses to empty output.
+    ///
+    /// Every unit produces output: a literal its byte, and a pair, whose
+    /// length field holds the length less `threshold + 1`, at least two
+    /// bytes even when the field is 0. So decoding takes time in proportion
+    /// to the input, and at most as many bytes of output per unit as the
+    /// length field can express, whatever the input holds.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// // A flags byte announcing eight pairs, each with a length field of
+    /// // 0, copying from one byte behind where decoding starts
+    /// let mut stream = vec![0x00];
+    /// for _ in 0..8 {
+    ///     stream.extend_from_slice(&[0xe7, 0x07 << 5]);
+    /// }
+    /// assert_eq!(Lzss::new().decompress(&stream)?, [0; 16]);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn decompress(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        
+        self.decode_slice(buffer, &mut output)?;
+        
+        Ok(output)
+    }
+
+    /// Like decompress(), but reserves `capacity` bytes of output up front,
+    /// as for a stream whose decompressed length is known from elsewhere.
+    /// The output may still grow past it.
+    pub fn decompress_with_capacity(&mut self, buffer: &[u8], capacity: usize) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::with_capacity(capacity);
+
+        self.decode_slice(buffer, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Like decompress(), for a stream that holds text: fails with
+    /// `LzssError::InvalidUtf8` at the first byte of the output that isn't
+    /// part of valid UTF-8, rather than replacing it.
+    ///
+    /// ```
+    /// use lzss::{Lzss, LzssError};
+    ///
+    /// let mut lzss = Lzss::new();
+    /// let text = lzss.compress("naïve café, naïve café".as_bytes())?;
+    /// assert_eq!(lzss.decompress_to_string(&text)?, "naïve café, naïve café");
+    ///
+    /// let binary = lzss.compress(b"ok\xff\xfe")?;
+    /// assert!(matches!(lzss.decompress_to_string(&binary), Err(LzssError::InvalidUtf8 { offset: 2 })));
+    /// # Ok::<(), LzssError>(())
+    /// ```
+    pub fn decompress_to_string(&mut self, buffer: &[u8]) -> Result<String, LzssError> {
+        let output = self.decompress(buffer)?;
+
+        String::from_utf8(output).map_err(|e| LzssError::InvalidUtf8 { offset: e.utf8_error().valid_up_to() })
+    }
+
+    /// Decompresses `src` into the start of `dst` and returns the number of
+    /// bytes written, or `LzssError::BufferTooSmall` if they don't fit.
+    pub fn decompress_into(&mut self, src: &[u8], dst: &mut [u8]) -> Result<usize, LzssError> {
+        let mut output = SliceSink::new(dst);
+
+        self.decode_slice(src, &mut output)?;
+
+        Ok(output.len())
+    }
+
+    /// Like decompress(), but fails with `LzssError::OutputLimitExceeded`
+    /// as soon as the output would exceed `max_output` bytes, and never
+    /// allocates more than that. Use this for untrusted input, where a
+    /// small stream can expand a great deal.
+    pub fn decompress_limited(&mut self, buffer: &[u8], max_output: usize) -> Result<Vec<u8>, LzssError> {
+        let mut output = LimitedSink::new(max_output);
+
+        self.decode_slice(buffer, &mut output)?;
+
+        Ok(output.into_inner())
+    }
+
+    /// Number of bytes decompress() would produce for `buffer`, found
+    /// without keeping the decompressed output. This is a u64 because a
+    /// small stream can expand past what a 32-bit usize can count.
+    pub fn decompressed_size(&mut self, buffer: &[u8]) -> Result<u64, LzssError> {
+        let mut output = CountingSink::default();
+
+        self.decode_slice(buffer, &mut output)?;
+
+        Ok(output.len())
+    }
+
+    /// Number of units, literals and pairs, in the compressed `buffer`:
+    /// as many as tokenize() gives for the data it came from. Only the
+    /// flags are read, skipping over each unit, so this is much cheaper than
+    /// decompressing. Pairs aren't checked, so a stream this accepts may
+    /// still fail to decompress. A stream that ends inside a unit, or on a
+    /// flags byte, fails with `LzssError::TruncatedStream` as it would there.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"count the units, not the bytes; count the units".repeat(20);
+    /// let mut lzss = Lzss::new();
+    /// let compressed = lzss.compress(&data)?;
+    /// assert_eq!(lzss.count_tokens(&compressed)?, lzss.tokenize(&data)?.len());
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn count_tokens(&self, buffer: &[u8]) -> Result<usize, LzssError> {
+        let mut count = 0;
+        let mut rest = buffer;
+        while !rest.is_empty() {
+            let (flags, units) = self.split_flags(rest).ok_or(LzssError::TruncatedStream)?;
+            if units.is_empty() {
+                return Err(LzssError::TruncatedStream);
+            }
+            rest = units;
+            for bit in 0..self.flag_bits {
+                if rest.is_empty() {
+                    break;
+                }
+                let len = if flags & (1 << bit) != 0 { 1 } else { self.pair_len() };
+                rest = rest.get(len..).ok_or(LzssError::TruncatedStream)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Decompresses everything `input` yields into `output` without
+    /// collecting the decompressed payload in memory. Reverse of
+    /// compress_stream(), and the decoder decompress() runs over a slice.
+    /// Each unit's bytes are written as soon as it is decoded, so as with
+    /// compress_stream() a `BufWriter` helps.
+    #[cfg(feature = "std")]
+    pub fn decompress_stream<R: Read, W: Write>(&mut self, input: R, output: W) -> Result<(), LzssError> {
+        self.decode(input, output)
+    }
+
+    /// Like compress(), but prefixes the output with the uncompressed length
+    /// as an 8-byte little-endian integer.
+    /// Empty input produces just the header.
+    pub fn compress_with_header(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        output.extend_from_slice(&(buffer.len() as u64).to_le_bytes());
+
+        self.encode_slice(buffer, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Reverse of compress_with_header(). The header is taken at its word:
+    /// a stream that decompresses to more bytes than it gives fails with
+    /// `LzssError::LengthMismatch` as soon as it runs past, without
+    /// producing the rest, and so does one that ends short of it.
+    ///
+    /// ```
+    /// use lzss::{Lzss, LzssError};
+    ///
+    /// // One flags byte marking five literals
+    /// let mut too_long = 3u64.to_le_bytes().to_vec();
+    /// too_long.extend_from_slice(&[0x1f, b'h', b'e', b'l', b'l', b'o']);
+    /// let result = Lzss::new().decompress_with_header(&too_long);
+    /// assert!(matches!(result, Err(LzssError::LengthMismatch { expected: 3 })));
+    ///
+    /// let mut too_short = too_long.clone();
+    /// too_short[..8].copy_from_slice(&10u64.to_le_bytes());
+    /// let result = Lzss::new().decompress_with_header(&too_short);
+    /// assert!(matches!(result, Err(LzssError::LengthMismatch { expected: 10 })));
+    ///
+    /// too_long[..8].copy_from_slice(&5u64.to_le_bytes());
+    /// assert_eq!(Lzss::new().decompress_with_header(&too_long)?, b"hello");
+    /// # Ok::<(), LzssError>(())
+    /// ```
+    pub fn decompress_with_header(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let expected = Self::peek_decompressed_len(buffer)?;
+        let mismatch = LzssError::LengthMismatch { expected };
+        // A length past what memory can hold can't be reached, so no limit
+        // is needed then. Otherwise stop at one byte past it.
+        let limit = usize::try_from(expected).ok().and_then(|len| len.checked_add(1)).unwrap_or(usize::MAX);
+
+        match self.decompress_limited(&buffer[Self::HEADER_LEN..], limit) {
+            Ok(output) if output.len() as u64 == expected => Ok(output),
+            Ok(_) | Err(LzssError::OutputLimitExceeded) => Err(mismatch),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like compress(), but prefixes the output with the CRC-32 of the
+    /// uncompressed data as a 4-byte little-endian integer.
+    /// Empty input produces just the checksum.
+    pub fn compress_checked(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = Vec::new();
+        output.extend_from_slice(&crc32(buffer).to_le_bytes());
+
+        self.encode_slice(buffer, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Reverse of compress_checked(). Fails with `LzssError::ChecksumMismatch`
+    /// if the decompressed data doesn't match the stored CRC-32.
+    pub fn decompress_checked(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let expected = match buffer.get(..Self::CHECKSUM_LEN) {
+            Some(checksum) => u32::from_le_bytes(checksum.try_into().unwrap()),
+            None => return Err(LzssError::TruncatedStream),
+        };
+
+        let output = self.decompress(&buffer[Self::CHECKSUM_LEN..])?;
+
+        let actual = crc32(&output);
+        if actual != expected {
+            return Err(LzssError::ChecksumMismatch { expected, actual });
+        }
+
+        Ok(output)
+    }
+
+    /// Like compress(), but prefixes the output with a byte identifying the
+    /// pair layout: the number of position bits, `window_bits`. From it
+    /// follow the pair size, 2 bytes up to 15 and 3 bytes from 16, and the
+    /// length bits, the rest of the pair. See with_params().
+    pub fn compress_with_format_id(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        let mut output = vec![self.window_bits as u8];
+
+        self.encode_slice(buffer, &mut output)?;
+
         Ok(output)
     }
+
+    /// Reverse of compress_with_format_id(). Fails with
+    /// `LzssError::InvalidParams` if the stream's pair layout isn't this
+    /// Lzss's. The lookahead and threshold aren't recorded, so they must
+    /// match as well.
+    pub fn decompress_with_format_id(&mut self, buffer: &[u8]) -> Result<Vec<u8>, LzssError> {
+        match buffer.first() {
+            Some(&id) if id as u32 == self.window_bits => self.decompress(&buffer[1..]),
+            Some(_) => Err(LzssError::InvalidParams),
+            None => Err(LzssError::TruncatedStream),
+        }
+    }
+
+    /// Reads the uncompressed length from a stream made by
+    /// compress_with_header() without decompressing it.
+    pub fn peek_decompressed_len(input: &[u8]) -> Result<u64, LzssError> {
+        match input.get(..Self::HEADER_LEN) {
+            Some(header) => Ok(u64::from_le_bytes(header.try_into().unwrap())),
+            None => Err(LzssError::TruncatedStream),
+        }
+    }
 }
 
 impl Default for Lzss {
@@ -371,3 +1815,576 @@ impl Default for Lzss {
         Self::new()
     }
 }
+
+/// Copies everything, including the state of a compression or
+/// decompression under way, so both copies carry on independently from
+/// there: the ring buffer, the match finder's trees or chains, and the
+/// pending code block. This allows trying out a continuation and going
+/// back.
+///
+/// ```
+/// use std::io::Write;
+/// use lzss::{Lzss, LzssDecoder};
+///
+/// let compressed = Lzss::new().compress(b"one two one two one")?;
+/// let (head, tail) = compressed.split_at(5);
+/// let mut decoder = LzssDecoder::new(Vec::new());
+/// decoder.write_all(head)?;
+///
+/// let mut branch = decoder.clone();
+/// branch.write_all(&Lzss::new().compress(b"one two one two one!")?[5..])?;
+/// decoder.write_all(tail)?;
+/// assert_eq!(decoder.finish()?, b"one two one two one");
+/// assert_eq!(branch.finish()?, b"one two one two one!");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Panics
+///
+/// If a finder from with_match_finder() can't be copied, because it
+/// doesn't implement `MatchFinder::clone_box()`. try_clone() returns None
+/// instead.
+impl Clone for Lzss {
+    fn clone(&self) -> Self {
+        self.try_clone().expect("match finder can't be cloned")
+    }
+}
+
+/// Compresses `data` with the default parameters. Use an `Lzss` directly to
+/// change parameters or to reuse its buffers across calls.
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, LzssError> {
+    Lzss::new().compress(data)
+}
+
+/// Decompresses `data` that was compressed with the default parameters.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, LzssError> {
+    Lzss::new().decompress(data)
+}
+
+/// Decompresses `data` that was compressed with the default parameters
+/// and checks that it is UTF-8 text.
+pub fn decompress_to_string(data: &[u8]) -> Result<String, LzssError> {
+    Lzss::new().decompress_to_string(data)
+}
+
+/// Decompresses `data` that was compressed with the default parameters,
+/// failing rather than producing more than `max_output` bytes.
+pub fn decompress_limited(data: &[u8], max_output: usize) -> Result<Vec<u8>, LzssError> {
+    Lzss::new().decompress_limited(data, max_output)
+}
+
+/// Decompresses what `input` yields with the default parameters, one byte
+/// per iteration, reading and decoding only as far as needed.
+#[cfg(feature = "std")]
+pub fn decompress_iter<R: Read>(input: R) -> DecompressIter<R> {
+    DecompressIter::new(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// `len` bytes of stretches of noise and of copies of what came
+    /// before, so that matches of all lengths and distances turn up.
+    fn sample(len: usize, seed: u32) -> Vec<u8> {
+        let mut x = seed | 1;
+        let mut next = move || {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x
+        };
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let run = (next() % 64) as usize + 1;
+            if next() % 3 == 0 || data.is_empty() {
+                data.extend((0..run).map(|_| next() as u8));
+            } else {
+                let from = next() as usize % data.len();
+                for i in 0..run {
+                    data.push(data[from + i]);
+                }
+            }
+        }
+        data.truncate(len);
+        data
+    }
+
+    /// Every window and threshold with_params_and_threshold() takes, up to
+    /// a threshold of 3, each with the shortest and longest lookahead it
+    /// allows.
+    fn all_params() -> impl Iterator<Item = (u32, usize, usize)> {
+        (8..=20).flat_map(|window_bits| {
+            (1..=3).flat_map(move |threshold| {
+                let longest = ((1 << Lzss::length_bits_for(window_bits)) + threshold).min((1 << window_bits) / 2);
+                [threshold + 1, longest].map(|lookahead| (window_bits, lookahead, threshold))
+            })
+        })
+    }
+
+    #[test]
+    fn round_trips_edge_sizes_for_all_params() {
+        for (window_bits, f, threshold) in all_params() {
+            let mut lzss = Lzss::with_params_and_threshold(window_bits, f, threshold).unwrap();
+            let n = 1 << window_bits;
+            for len in [0, 1, f, n, n + f] {
+                let data = sample(len, len as u32 ^ window_bits);
+                let compressed = lzss.compress(&data).unwrap();
+                assert_eq!(
+                    lzss.decompress(&compressed).unwrap(),
+                    data,
+                    "{len} bytes, window bits {window_bits}, lookahead {f}, threshold {threshold}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_one_repeated_byte() {
+        for (window_bits, f, threshold) in all_params().filter(|&(window_bits, ..)| window_bits <= 12) {
+            let mut lzss = Lzss::with_params_and_threshold(window_bits, f, threshold).unwrap();
+            let data = vec![b'a'; (1 << window_bits) + f];
+            let compressed = lzss.compress(&data).unwrap();
+            assert_eq!(lzss.decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    /// Offsets in `stream` at which a unit ends, so that a stream cut
+    /// there is still whole.
+    fn unit_ends(lzss: &Lzss, stream: &[u8]) -> Vec<usize> {
+        let mut ends = Vec::new();
+        let mut at = 0;
+        while at < stream.len() {
+            let flags = stream[at];
+            at += 1;
+            for bit in 0..8 {
+                if at == stream.len() {
+                    break;
+                }
+                at += if flags & (1 << bit) != 0 { 1 } else { lzss.pair_len() };
+                ends.push(at);
+            }
+        }
+        ends
+    }
+
+    #[test]
+    fn truncation_at_every_offset_is_detected() {
+        for window_bits in [11, 16] {
+            let mut lzss = Lzss::with_params(window_bits, 24).unwrap();
+            let data = sample(3000, window_bits);
+            let compressed = lzss.compress(&data).unwrap();
+            let ends = unit_ends(&lzss, &compressed);
+            assert_eq!(ends.last(), Some(&compressed.len()));
+
+            for cut in 0..=compressed.len() {
+                let result = lzss.decompress(&compressed[..cut]);
+                if cut == 0 || ends.contains(&cut) {
+                    let output = result.unwrap();
+                    assert_eq!(output, data[..output.len()], "cut at {cut}");
+                } else {
+                    assert!(matches!(result, Err(LzssError::TruncatedStream)), "cut at {cut}: {result:?}");
+                }
+
+                #[cfg(feature = "std")]
+                {
+                    let streamed = lzss.decompress_stream(&compressed[..cut], Vec::new());
+                    assert_eq!(streamed.is_ok(), cut == 0 || ends.contains(&cut), "streamed cut at {cut}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn back_reference_before_the_start_is_rejected() {
+        // Flags announcing a pair, and a pair copying from position 0, 2024
+        // bytes before where decoding starts, where only 24 are populated
+        let stream = [0x00, 0x00, 0x00];
+        let result = decompress(&stream);
+        assert!(matches!(result, Err(LzssError::InvalidBackreference { pos: 0, len: 2 })), "{result:?}");
+
+        // The F zero bytes in front may be copied, but not one more
+        assert_eq!(decompress(&[0x00, 0xd0, 0xe0]).unwrap(), [0, 0]);
+        let result = decompress(&[0x00, 0xcf, 0xe0]);
+        assert!(matches!(result, Err(LzssError::InvalidBackreference { pos: 1999, len: 2 })), "{result:?}");
+    }
+
+    /// Collects what is written to it, counting the calls.
+    #[cfg(feature = "std")]
+    #[derive(Default)]
+    struct CountingWriter {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn code_is_written_a_block_at_a_time() {
+        let data = sample(20_000, 9);
+        let mut lzss = Lzss::new();
+        let compressed = lzss.compress(&data).unwrap();
+
+        let mut writer = CountingWriter::default();
+        lzss.compress_stream(&data[..], &mut writer).unwrap();
+        assert_eq!(writer.data, compressed);
+        // One write per eight units, rather than one per byte
+        assert_eq!(writer.writes, lzss.count_tokens(&compressed).unwrap().div_ceil(8));
+        assert!(writer.writes * 8 < compressed.len());
+    }
+
+    #[test]
+    fn checksum_catches_flipped_bits() {
+        let data = sample(2000, 12);
+        let mut lzss = Lzss::new();
+        let checked = lzss.compress_checked(&data).unwrap();
+        assert_eq!(lzss.decompress_checked(&checked).unwrap(), data);
+
+        // The byte after the checksum and the first flags byte is a literal
+        for offset in [0, 5] {
+            let mut damaged = checked.clone();
+            damaged[offset] ^= 0x10;
+            let result = lzss.decompress_checked(&damaged);
+            assert!(matches!(result, Err(LzssError::ChecksumMismatch { .. })), "offset {offset}: {result:?}");
+        }
+
+        // Anywhere else, a flip may make the stream fail some other way, but
+        // it never decodes to something else unnoticed
+        for offset in 0..checked.len() {
+            for bit in 0..8 {
+                let mut damaged = checked.clone();
+                damaged[offset] ^= 1 << bit;
+                if let Ok(output) = lzss.decompress_checked(&damaged) {
+                    assert_eq!(output, data, "bit {bit} at offset {offset}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn into_slices_of_every_fit() {
+        let data = sample(1500, 14);
+        let mut lzss = Lzss::new();
+        let compressed = lzss.compress(&data).unwrap();
+
+        let mut exact = vec![0; compressed.len()];
+        assert_eq!(lzss.compress_into(&data, &mut exact).unwrap(), compressed.len());
+        assert_eq!(exact, compressed);
+        let mut exact = vec![0; data.len()];
+        assert_eq!(lzss.decompress_into(&compressed, &mut exact).unwrap(), data.len());
+        assert_eq!(exact, data);
+
+        let mut short = vec![0; compressed.len() - 1];
+        assert!(matches!(lzss.compress_into(&data, &mut short), Err(LzssError::BufferTooSmall)));
+        let mut short = vec![0; data.len() - 1];
+        assert!(matches!(lzss.decompress_into(&compressed, &mut short), Err(LzssError::BufferTooSmall)));
+
+        let mut roomy = vec![0xaa; compressed.len() + 100];
+        assert_eq!(lzss.compress_into(&data, &mut roomy).unwrap(), compressed.len());
+        assert_eq!(roomy[..compressed.len()], compressed);
+        assert!(roomy[compressed.len()..].iter().all(|&b| b == 0xaa));
+        let mut roomy = vec![0xaa; data.len() + 100];
+        assert_eq!(lzss.decompress_into(&compressed, &mut roomy).unwrap(), data.len());
+        assert_eq!(roomy[..data.len()], data);
+        assert!(roomy[data.len()..].iter().all(|&b| b == 0xaa));
+    }
+
+    #[test]
+    fn reuse_matches_fresh_instances() {
+        let a = sample(5000, 15);
+        let b = b"something else entirely, something else".repeat(40);
+        for fresh in [Lzss::new, || Lzss::with_level(9).unwrap()] {
+            let mut reused = fresh();
+            for input in [&a, &b, &a] {
+                let compressed = reused.compress(input).unwrap();
+                assert_eq!(compressed, fresh().compress(input).unwrap());
+                assert_eq!(reused.decompress(&compressed).unwrap(), *input);
+            }
+        }
+    }
+
+    #[test]
+    fn output_limit_stops_an_expansive_stream() {
+        // A megabyte of zeros, in pairs of 24 bytes each
+        let bomb = compress(&vec![0; 1 << 20]).unwrap();
+        assert!(bomb.len() < 100_000);
+
+        let result = decompress_limited(&bomb, 4096);
+        assert!(matches!(result, Err(LzssError::OutputLimitExceeded)), "{:?}", result.map(|v| v.len()));
+        assert!(matches!(decompress_limited(&bomb, (1 << 20) - 1), Err(LzssError::OutputLimitExceeded)));
+        assert_eq!(decompress_limited(&bomb, 1 << 20).unwrap().len(), 1 << 20);
+    }
+
+    /// Hands out `data` in pieces of varying size, up to 9973 bytes.
+    #[cfg(feature = "std")]
+    struct Chunked<'a> {
+        data: &'a [u8],
+        reads: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl Read for Chunked<'_> {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            self.reads += 1;
+            let n = (self.reads * 7919 % 9973 + 1).min(out.len()).min(self.data.len());
+            out[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_of_ten_megabytes_matches_compress() {
+        let data = sample(10 << 20, 1);
+        let mut lzss = Lzss::new();
+        let expected = lzss.compress(&data).unwrap();
+
+        let mut reader = Chunked { data: &data, reads: 0 };
+        let mut streamed = Vec::new();
+        lzss.compress_stream(&mut reader, &mut streamed).unwrap();
+        assert!(reader.reads > 1000);
+        assert!(streamed == expected, "streamed output differs from compress()");
+
+        let mut decompressed = Vec::new();
+        lzss.decompress_stream(Chunked { data: &streamed, reads: 0 }, &mut decompressed).unwrap();
+        assert!(decompressed == data, "streamed round trip differs");
+    }
+
+    #[test]
+    fn header_gives_the_decompressed_length() {
+        for len in [0, 1, 24, 1000, 70_000] {
+            let data = sample(len, 7);
+            let mut lzss = Lzss::new();
+            let compressed = lzss.compress_with_header(&data).unwrap();
+            assert_eq!(Lzss::peek_decompressed_len(&compressed).unwrap(), len as u64);
+            assert_eq!(lzss.decompress_with_header(&compressed).unwrap(), data);
+            assert_eq!(compressed[8..], lzss.compress(&data).unwrap());
+        }
+        assert!(matches!(Lzss::peek_decompressed_len(&[0; 7]), Err(LzssError::TruncatedStream)));
+    }
+
+    #[test]
+    fn lazy_matching_is_never_worse_on_the_corpus() {
+        let corpus: [&[u8]; 4] = [
+            include_bytes!("../testdata/lzss.c.txt"),
+            include_bytes!("../testdata/lzss.c.txt.lzss"),
+            &sample(50_000, 16),
+            &b"abcde bcdefgh abcdefgh cdefghij ".repeat(200),
+        ];
+        for data in corpus {
+            for mut lzss in [Lzss::new(), Lzss::lzss_c(), Lzss::with_params(16, 255).unwrap()] {
+                let greedy = lzss.compress(data).unwrap();
+                let mut lazy = lzss.with_lazy_matching(true);
+                let compressed = lazy.compress(data).unwrap();
+                assert!(compressed.len() <= greedy.len(), "{} lazy vs {} greedy", compressed.len(), greedy.len());
+                assert_eq!(lazy.decompress(&compressed).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn stats_account_for_every_input_byte() {
+        for data in [Vec::new(), b"x".to_vec(), sample(20_000, 17), vec![b'z'; 5000]] {
+            for mut lzss in [Lzss::new(), Lzss::new().with_lazy_matching(true), Lzss::with_level(1).unwrap()] {
+                let (compressed, stats) = lzss.compress_with_stats(&data).unwrap();
+                assert_eq!(stats.literals + stats.matched_bytes, data.len() as u64);
+                assert_eq!((stats.literals + stats.matches) as usize, lzss.count_tokens(&compressed).unwrap());
+                assert!(stats.longest_match <= lzss.f);
+                assert_eq!(stats.match_length_histogram.iter().sum::<u64>(), stats.matches);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_input_is_an_empty_stream() {
+        assert_eq!(compress(&[]).unwrap(), b"");
+        assert_eq!(decompress(&[]).unwrap(), b"");
+
+        // The headers still go out, and still read back as nothing
+        let mut lzss = Lzss::new();
+        let with_header = lzss.compress_with_header(&[]).unwrap();
+        assert_eq!(with_header, 0u64.to_le_bytes());
+        assert_eq!(lzss.decompress_with_header(&with_header).unwrap(), b"");
+        let checked = lzss.compress_checked(&[]).unwrap();
+        assert_eq!(checked, crc32(&[]).to_le_bytes());
+        assert_eq!(lzss.decompress_checked(&checked).unwrap(), b"");
+        let framed = lzss.compress_framed(&[]).unwrap();
+        assert!(!framed.is_empty());
+        assert_eq!(lzss.decompress_framed(&framed).unwrap(), b"");
+    }
+
+    #[test]
+    fn thresholds_one_to_three_round_trip() {
+        let data = sample(30_000, 26);
+        for threshold in 1..=3 {
+            let mut lzss = Lzss::with_params(12, 17).unwrap().with_threshold(threshold).unwrap();
+            let (compressed, stats) = lzss.compress_with_stats(&data).unwrap();
+            assert_eq!(lzss.decompress(&compressed).unwrap(), data, "threshold {threshold}");
+            // No match of `threshold` bytes or fewer is sent
+            assert!(stats.match_length_histogram.iter().take(threshold + 1).all(|&count| count == 0));
+            assert!(stats.matches > 0);
+        }
+    }
+
+    #[test]
+    fn encoding_across_the_wraparound_stays_in_bounds() {
+        // The finders' debug assertions check every text_buf index on the way
+        for (window_bits, f) in [(8, 18), (11, 24), (11, 33), (12, 17)] {
+            let n = 1 << window_bits;
+            for len in [n - 1, n, n + 1, n + f, 3 * n + f + 1] {
+                for data in [sample(len, len as u32), vec![0xff; len]] {
+                    for mut lzss in [
+                        Lzss::with_params(window_bits, f).unwrap(),
+                        Lzss::with_params(window_bits, f).unwrap().with_lazy_matching(true),
+                        Lzss::with_params(window_bits, f).unwrap().with_match_strategy(MatchStrategy::HashChain),
+                        Lzss::with_params(window_bits, f).unwrap().with_dictionary(&data[len / 2..]),
+                    ] {
+                        let compressed = lzss.compress(&data).unwrap();
+                        assert_eq!(lzss.decompress(&compressed).unwrap(), data, "{len} bytes in a window of {n}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decompress_to_string_checks_utf8() {
+        let text = "Grüße, こんにちは, Grüße, こんにちは";
+        let compressed = compress(text.as_bytes()).unwrap();
+        assert_eq!(decompress_to_string(&compressed).unwrap(), text);
+        assert_eq!(decompress_to_string(&[]).unwrap(), "");
+
+        // Binary data, and text cut off inside a character
+        let binary = compress(&[0x00, 0x9f, 0x92, 0x96]).unwrap();
+        assert!(matches!(decompress_to_string(&binary), Err(LzssError::InvalidUtf8 { offset: 1 })));
+        let cut = compress(&text.as_bytes()[..text.len() - 1]).unwrap();
+        let offset = text.len() - "は".len();
+        assert!(matches!(decompress_to_string(&cut), Err(LzssError::InvalidUtf8 { offset: o }) if o == offset));
+    }
+
+    /// Streams the decompress fuzz target found that expand the most
+    /// for their size, nearly all pairs of the longest lengths.
+    const FUZZ_FOUND: [&[u8]; 5] = [
+        &[0x40, 0xd5, 0xf5, 0xf9, 0xff, 0xf5, 0xf9],
+        &[0x16, 0xde, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0xff, 0xff, 0xf5, 0xff],
+        &[0x2c, 0xd5, 0xf5, 0xf5, 0xf9],
+        &[0x29, 0x0e, 0xd4, 0xff, 0xfe, 0xff, 0x0a],
+        &[0x2b, 0xdf, 0x2f, 0xd4, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+    ];
+
+    #[test]
+    fn every_unit_makes_progress() {
+        let lzss = Lzss::new();
+        for stream in FUZZ_FOUND {
+            // One byte for each literal, and for each pair its length field
+            // plus threshold + 1, never 0
+            let mut expected = 0;
+            let mut units = 0;
+            let mut rest = stream;
+            while let Some((&flags, mut tail)) = rest.split_first() {
+                for bit in 0..8 {
+                    if tail.is_empty() {
+                        break;
+                    }
+                    if flags & (1 << bit) != 0 {
+                        expected += 1;
+                        tail = &tail[1..];
+                    } else {
+                        expected += (tail[1] & 0x1f) as usize + lzss.threshold + 1;
+                        tail = &tail[2..];
+                    }
+                    units += 1;
+                }
+                rest = tail;
+            }
+            assert_eq!(units, lzss.count_tokens(stream).unwrap());
+            assert_eq!(decompress(stream).unwrap().len(), expected, "{stream:02x?}");
+        }
+    }
+
+    #[test]
+    fn decompressing_sets_up_only_a_window() {
+        let data = sample(10_000, 54);
+        let compressed = Lzss::with_params(20, 17).unwrap().compress(&data).unwrap();
+        let mut lzss = Lzss::with_params(20, 17).unwrap();
+        assert_eq!(lzss.decompress(&compressed).unwrap(), data);
+        assert!(lzss.text_buf.is_empty());
+        assert_eq!(lzss.dec.ring.len(), 1 << 20);
+    }
+
+    #[test]
+    fn decompressing_leaves_an_unfinished_compression_alone() {
+        let data = sample(20_000, 72);
+        let other = compress(&sample(5000, 27)).unwrap();
+        let mut lzss = Lzss::new();
+        let mut out = Vec::new();
+        for piece in data.chunks(3000) {
+            lzss.compress_update(piece, &mut out).unwrap();
+            assert_eq!(lzss.decompress(&other).unwrap(), sample(5000, 27));
+        }
+        lzss.compress_finish(&mut out).unwrap();
+        assert!(out == compress(&data).unwrap());
+    }
+
+    #[test]
+    fn clones_taken_mid_encode_go_their_own_ways() {
+        let prefix = sample(10_000, 3);
+        let (a, b) = (sample(5_000, 5), [&prefix[..2_000], &sample(3_000, 7)].concat());
+        let encoders = [
+            Lzss::new(),
+            Lzss::new().with_lazy_matching(true),
+            Lzss::new().with_match_strategy(MatchStrategy::HashChain),
+            Lzss::with_params(16, 66).unwrap().with_dictionary(&prefix[..500]),
+            Lzss::new().with_flag_bits(16).unwrap(),
+        ];
+        for lzss in encoders {
+            let mut original = lzss.clone();
+            let mut out = Vec::new();
+            // Split mid-unit, with input still in the lookahead
+            original.compress_update(&prefix[..9_999], &mut out).unwrap();
+            let mut copy = original.clone();
+            let mut copy_out = out.clone();
+
+            // The original runs to the end first, so anything the two still
+            // shared would show up in the copy's output.
+            for (lzss, out, suffix) in [(&mut original, &mut out, &a), (&mut copy, &mut copy_out, &b)] {
+                lzss.compress_update(&prefix[9_999..], out).unwrap();
+                lzss.compress_update(suffix, out).unwrap();
+                lzss.compress_finish(out).unwrap();
+            }
+            let mut fresh = lzss.clone();
+            assert_eq!(out, fresh.compress(&[&prefix[..], &a].concat()).unwrap());
+            assert_eq!(copy_out, fresh.compress(&[&prefix[..], &b].concat()).unwrap());
+            assert_ne!(out, copy_out);
+            assert_eq!(copy.decompress(&copy_out).unwrap(), [&prefix[..], &b].concat());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips_any_input(data in proptest::collection::vec(any::<u8>(), 0..5000)) {
+            prop_assert_eq!(decompress(&compress(&data)?)?, data);
+        }
+
+        #[test]
+        fn round_trips_any_input_lazily(data in proptest::collection::vec(0..4u8, 0..5000)) {
+            let mut lzss = Lzss::with_params(8, 18)?.with_lazy_matching(true);
+            let compressed = lzss.compress(&data)?;
+            prop_assert_eq!(lzss.decompress(&compressed)?, data);
+        }
+    }
+}
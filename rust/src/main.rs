@@ -1,97 +1,688 @@
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Write};
-use std::path::Path;
 use std::env;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-mod lzss_stream;
-use crate::lzss_stream::Lzss;
+use lzss::{ArchiveEntry, CompressionStats, CompressionSummary, FrameHeader, Lzss, LzssError};
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        eprintln!("Usage: {} <compress|decompress> <input_file> [output_file]", args[0]);
+/// Path that stands for stdin as input or stdout as output
+const STDIO: &str = "-";
+
+/// Size of the blocks framed output is compressed in, each on its own
+/// thread, when built with the parallel feature. Fixed, so the output
+/// doesn't depend on --threads.
+#[cfg(feature = "parallel")]
+const BLOCK_SIZE: usize = 1 << 20;
+
+/// Bytes of data between the checkpoints of --checkpointed output
+#[cfg(feature = "checkpointed")]
+const CHECKPOINT_INTERVAL: usize = 64 << 10;
+
+/// Exit status when an input file doesn't exist. When several files fail,
+/// the first one's status is used; 1 stands for any other failure,
+/// including bad usage.
+const EXIT_NOT_FOUND: i32 = 2;
+
+/// Exit status when a file can't be read or written for lack of permission
+const EXIT_PERMISSION_DENIED: i32 = 3;
+
+/// Exit status when an input to decompress isn't LZSS data, or is corrupt
+const EXIT_NOT_LZSS: i32 = 4;
+
+/// Exit status when an input to decompress ends before its data does
+const EXIT_TRUNCATED: i32 = 5;
+
+/// Flags given on the command line
+#[derive(Default)]
+struct Options {
+    /// suppress diagnostics
+    quiet: bool,
+    /// print the projected ratio instead of compressing
+    estimate: bool,
+    /// print how the input was encoded after compressing it
+    stats: bool,
+    /// write the classic headerless format, and read input as it; without
+    /// this, input to decompress must start with the framed magic
+    raw: bool,
+    /// write and read the checkpointed format, which locates damage; only
+    /// with the checkpointed feature
+    checkpointed: bool,
+    /// overwrite output files that already exist
+    force: bool,
+    /// leave the input's name out of the framed header
+    no_name: bool,
+    /// neither store nor restore modification times
+    no_time: bool,
+    /// remove each input file once its output is written; unlike gzip,
+    /// inputs are kept unless this is given
+    delete: bool,
+    /// effort preset from 1 to 9, or None for the classic parameters
+    level: Option<u32>,
+    /// most threads to compress with, or None for one per core
+    threads: Option<usize>,
+}
+
+/// Prints a diagnostic to stderr unless --quiet was given. Diagnostics go to
+/// stderr so they never mix with data written to stdout.
+macro_rules! info {
+    ($options:expr, $($arg:tt)*) => {
+        if !$options.quiet {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+fn main() {
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+
+    let mut options = Options::default();
+    let mut output_file = None;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-q" | "--quiet" => options.quiet = true,
+            "--estimate" => options.estimate = true,
+            "--stats" => options.stats = true,
+            "--raw" => options.raw = true,
+            "--checkpointed" if cfg!(feature = "checkpointed") => options.checkpointed = true,
+            "--checkpointed" => {
+                eprintln!("--checkpointed needs a build with the checkpointed feature");
+                std::process::exit(1);
+            }
+            "-f" | "--force" => options.force = true,
+            "-n" | "--no-name" => options.no_name = true,
+            "--no-time" => options.no_time = true,
+            "--delete" => options.delete = true,
+            "-k" | "--keep" => options.delete = false,
+            "--level" => match args.next().and_then(|level| level.parse().ok()) {
+                Some(level @ 1..=9) => options.level = Some(level),
+                _ => {
+                    eprintln!("--level needs a number from 1 to 9");
+                    std::process::exit(1);
+                }
+            },
+            "--threads" => match args.next().and_then(|threads| threads.parse().ok()) {
+                Some(threads @ 1..) if cfg!(feature = "parallel") => options.threads = Some(threads),
+                Some(1..) => {
+                    eprintln!("--threads needs a build with the parallel feature");
+                    std::process::exit(1);
+                }
+                _ => {
+                    eprintln!("--threads needs a number of at least 1");
+                    std::process::exit(1);
+                }
+            },
+            "-1" | "-2" | "-3" | "-4" | "-5" | "-6" | "-7" | "-8" | "-9" => options.level = arg[1..].parse().ok(),
+            "-o" | "--output" => match args.next() {
+                Some(path) => output_file = Some(path),
+                None => {
+                    eprintln!("{} needs a path", arg);
+                    std::process::exit(1);
+                }
+            },
+            _ if arg.starts_with('-') && arg != STDIO => {
+                eprintln!("Unknown option: {}", arg);
+                std::process::exit(1);
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.is_empty() {
+        eprintln!("Usage: {} [-q|--quiet] [--estimate] [--stats] [--raw] [--checkpointed] [-f|--force] [-n|--no-name] [--no-time] [--delete|-k|--keep] [-1..-9|--level <n>] [--threads <n>] [-o|--output <file|->] <compress|decompress|auto|verify|extract> [input_file|- [output_file|-] | input_file...]", program);
         std::process::exit(1);
     }
 
-    let command = &args[1];
-    let input_file = &args[2];
-    let output_file = if args.len() > 3 {
-        args[3].clone()
-    } else {
-        match command.as_str() {
-            "compress" => format!("{}.lzss", input_file),
-            "decompress" => {
-                if input_file.ends_with(".lzss") {
-                    input_file.trim_end_matches(".lzss").to_string()
-                } else {
-                    format!("{}.decompressed", input_file)
+    let command = positional.remove(0);
+    if !["compress", "decompress", "auto", "verify", "extract"].contains(&command.as_str()) {
+        eprintln!("Invalid command. Use 'compress', 'decompress', 'auto', 'verify' or 'extract'");
+        std::process::exit(1);
+    }
+    if command != "compress" && options.estimate {
+        eprintln!("--estimate only applies to compress");
+        std::process::exit(1);
+    }
+    if command != "compress" && options.stats {
+        eprintln!("--stats only applies to compress");
+        std::process::exit(1);
+    }
+
+    // Two paths are an input and its output, as they always were; a batch
+    // of inputs takes three or more
+    if positional.len() == 2 && command != "verify" {
+        if output_file.is_some() {
+            eprintln!("--output can't be used with an output_file argument");
+            std::process::exit(1);
+        }
+        output_file = positional.pop();
+    }
+    let input_files = if positional.is_empty() { vec![STDIO.to_string()] } else { positional };
+    if output_file.is_some() && input_files.len() > 1 {
+        eprintln!("--output can only be used with a single input file");
+        std::process::exit(1);
+    }
+
+    // Keep going past a file that fails, so one bad file doesn't stop the rest
+    let mut failed = 0;
+    let mut exit_status = 0;
+    // Byte counts are u64, as a usize may be 32 bits and the files together
+    // can be larger than that, even when each fits in memory.
+    let mut total_in: u64 = 0;
+    let mut total_out: u64 = 0;
+    for input_file in &input_files {
+        // A directory is compressed into an archive of its files
+        let is_dir = input_file != STDIO && Path::new(input_file).is_dir();
+        if is_dir && (command == "compress" || command == "auto") {
+            match compress_dir(input_file, output_file.as_deref(), &options) {
+                Ok((input_size, output_size)) => {
+                    total_in += input_size;
+                    total_out += output_size;
+                }
+                Err(e) => {
+                    let (message, status) = describe_error(&e, &command);
+                    eprintln!("Error: {}: {}", input_file, message);
+                    failed += 1;
+                    if exit_status == 0 {
+                        exit_status = status;
+                    }
                 }
             }
-            _ => {
-                eprintln!("Invalid command. Use 'compress' or 'decompress'");
-                std::process::exit(1);
+            continue;
+        }
+
+        let input_data = match read_input(input_file) {
+            Ok(input_data) => input_data,
+            Err(e) => {
+                let (message, status) = describe_read_error(&e);
+                eprintln!("Error: {}: {}", input_file, message);
+                failed += 1;
+                if exit_status == 0 {
+                    exit_status = status;
+                }
+                continue;
+            }
+        };
+        let result = (|| -> Result<(u64, u64), LzssError> {
+            // "auto" decompresses anything with the magic, and also a .lzss
+            // file without it, which fails as not LZSS unless --raw says it
+            // is in the classic format
+            let (command, framed) = match command.as_str() {
+                "auto" if Lzss::is_archive(&input_data) => ("extract", false),
+                "auto" if Lzss::is_framed(&input_data) => ("decompress", true),
+                "auto" if input_file.ends_with(".lzss") => ("decompress", !options.raw && !options.checkpointed),
+                "auto" | "compress" => ("compress", !options.raw && !options.checkpointed),
+                // Input without the magic is only taken for the classic
+                // format with --raw; any bytes decode as that, so without
+                // it they are refused as not LZSS
+                command => (command, !options.raw && !options.checkpointed),
+            };
+            if command == "verify" {
+                return verify_file(input_file, &input_data, framed, &options);
+            }
+            if command == "extract" {
+                let dest = output_file.clone().unwrap_or_else(|| default_output("decompress", input_file, None));
+                return extract_file(&input_data, &dest, &options);
+            }
+            // A name stored at compression is restored next to the input. Any
+            // problem with the header is left for decompress_file() to report.
+            let stored_name = match command {
+                "decompress" if framed => Lzss::peek_frame_header(&input_data).ok().and_then(|header| header.name),
+                _ => None,
+            };
+            let output_file = output_file.clone()
+                .unwrap_or_else(|| default_output(command, input_file, stored_name.as_deref()));
+
+            if options.estimate {
+                return estimate_file(input_file, &input_data, &options);
+            }
+            let sizes = if command == "compress" {
+                let header = framed.then(|| FrameHeader {
+                    name: stored_name_for(input_file, &options),
+                    mtime: stored_mtime_for(input_file, &options),
+                });
+                compress_file(&input_data, &output_file, header.as_ref(), &options)?
+            } else {
+                decompress_file(&input_data, &output_file, framed, &options)?
+            };
+            // Only reached once the output is complete and flushed
+            if options.delete {
+                remove_input(input_file, &output_file, &options)?;
+            }
+            Ok(sizes)
+        })();
+
+        match result {
+            Ok((input_size, output_size)) => {
+                total_in += input_size;
+                total_out += output_size;
+            }
+            // The reader of stdout went away, as with `| head`; there is no
+            // one left to tell.
+            Err(LzssError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => std::process::exit(0),
+            Err(e) => {
+                let (message, status) = describe_error(&e, &command);
+                eprintln!("Error: {}: {}", input_file, message);
+                failed += 1;
+                if exit_status == 0 {
+                    exit_status = status;
+                }
             }
         }
-    };
+    }
 
-    match command.as_str() {
-        "compress" => compress_file(input_file, &output_file),
-        "decompress" => decompress_file(input_file, &output_file),
-        _ => {
-            eprintln!("Invalid command. Use 'compress' or 'decompress'");
-            std::process::exit(1);
+    if input_files.len() > 1 {
+        info!(options, "Total: {} of {} files, {} bytes -> {} bytes",
+              input_files.len() - failed, input_files.len(), total_in, total_out);
+    }
+    if failed > 0 {
+        std::process::exit(exit_status);
+    }
+}
+
+/// What to say about an input file that couldn't be read, and the exit
+/// status for it
+fn describe_read_error(e: &io::Error) -> (String, i32) {
+    match e.kind() {
+        io::ErrorKind::NotFound => ("no such file or directory".to_string(), EXIT_NOT_FOUND),
+        io::ErrorKind::PermissionDenied => ("permission denied reading it".to_string(), EXIT_PERMISSION_DENIED),
+        _ => (format!("cannot read it: {}", e), 1),
+    }
+}
+
+/// What to say about a failure of `command` once the input was read, and
+/// the exit status for it
+fn describe_error(e: &LzssError, command: &str) -> (String, i32) {
+    match e {
+        // The input exists, so this is the output's directory
+        LzssError::Io(e) if e.kind() == io::ErrorKind::NotFound => (format!("cannot write the output: {}", e), 1),
+        LzssError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            (format!("permission denied: {}", e), EXIT_PERMISSION_DENIED)
+        }
+        LzssError::BadMagic if command == "extract" => ("not an LZSS archive".to_string(), EXIT_NOT_LZSS),
+        LzssError::BadMagic => (
+            "not an LZSS file, or data follows its last LZSS frame; use --raw for the classic headerless format".to_string(),
+            EXIT_NOT_LZSS,
+        ),
+        LzssError::TruncatedStream => (
+            "compressed data is truncated; the file is probably incomplete".to_string(),
+            EXIT_TRUNCATED,
+        ),
+        LzssError::InvalidBackreference { .. }
+        | LzssError::ChecksumMismatch { .. }
+        | LzssError::InvalidBlockIndex
+        | LzssError::CorruptedNear { .. }
+        | LzssError::LengthMismatch { .. } => {
+            (format!("not valid LZSS data, or corrupt: {}", e), EXIT_NOT_LZSS)
+        }
+        e => (e.to_string(), 1),
+    }
+}
+
+/// Where the result for `input_file` goes when no output was given: stdout
+/// for stdin, otherwise `stored_name` in the input's directory if the header
+/// had one, or else the input name with `.lzss` added or removed.
+fn default_output(command: &str, input_file: &str, stored_name: Option<&str>) -> String {
+    if input_file == STDIO {
+        STDIO.to_string()
+    } else if let Some(name) = stored_name {
+        Path::new(input_file).with_file_name(name).to_string_lossy().into_owned()
+    } else if command == "compress" {
+        format!("{}.lzss", input_file)
+    } else if input_file.ends_with(".lzss") {
+        input_file.trim_end_matches(".lzss").to_string()
+    } else {
+        format!("{}.decompressed", input_file)
+    }
+}
+
+/// The name to keep in the header when compressing `input_file`: its last
+/// component, unless it is stdin, --no-name was given or it isn't UTF-8.
+fn stored_name_for(input_file: &str, options: &Options) -> Option<String> {
+    if input_file == STDIO || options.no_name {
+        return None;
+    }
+    Path::new(input_file).file_name()?.to_str().map(str::to_string)
+}
+
+/// The modification time to keep in the header when compressing
+/// `input_file`, unless it is stdin, --no-time was given or the platform
+/// can't tell.
+fn stored_mtime_for(input_file: &str, options: &Options) -> Option<u64> {
+    if input_file == STDIO || options.no_time {
+        return None;
+    }
+    let modified = fs::metadata(input_file).and_then(|metadata| metadata.modified()).ok()?;
+    modified.duration_since(SystemTime::UNIX_EPOCH).ok().map(|since| since.as_secs())
+}
+
+/// An Lzss with the parameters of --level, or the classic ones without it.
+/// Framed streams carry their parameters, so this only matters for
+/// decompression of classic ones.
+fn new_lzss(options: &Options) -> Result<Lzss, LzssError> {
+    match options.level {
+        Some(level) => Lzss::with_level(level),
+        None => Ok(Lzss::new()),
+    }
+}
+
+/// The whole of an input file
+enum Input {
+    Read(Vec<u8>),
+    /// a regular file mapped into memory, so a large one isn't copied
+    #[cfg(feature = "memmap2")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Read(data) => data,
+            #[cfg(feature = "memmap2")]
+            Input::Mapped(map) => map,
         }
     }
 }
 
-fn compress_file<P: AsRef<Path>>(input_path: P, output_path: P) -> io::Result<()> {
-    // Read the entire input file into memory
-    let mut input_file = File::open(&input_path)?;
-    let mut input_data = Vec::new();
-    input_file.read_to_end(&mut input_data)?;
+/// Reads the whole of a file, or of stdin for "-". With the memmap2
+/// feature, a non-empty regular file is mapped instead.
+fn read_input(path: &str) -> io::Result<Input> {
+    let mut data = Vec::new();
+    if path == STDIO {
+        io::stdin().lock().read_to_end(&mut data)?;
+        return Ok(Input::Read(data));
+    }
+    let mut file = File::open(path)?;
+    #[cfg(feature = "memmap2")]
+    {
+        let metadata = file.metadata()?;
+        if metadata.is_file() && metadata.len() > 0 {
+            // SAFETY: the map is only read. If the file is truncated while
+            // it is mapped, as by another program, reading past the new end
+            // kills the process with SIGBUS, as for any tool that maps its
+            // input; it can't be turned into bad output.
+            return Ok(Input::Mapped(unsafe { memmap2::Mmap::map(&file)? }));
+        }
+    }
+    file.read_to_end(&mut data)?;
+    Ok(Input::Read(data))
+}
+
+/// The file output for `path` is written to before it is renamed into
+/// place: a hidden name in the same directory, so the rename stays on one
+/// file system.
+fn temp_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
 
-    let input_size = input_data.len();
-    println!("Reading file: {} bytes", input_size);
+/// Fails if `path` exists, unless `force` is set.
+fn check_overwrite(path: &str, force: bool) -> io::Result<()> {
+    if !force && fs::symlink_metadata(path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+            format!("{} already exists; use --force to overwrite it", path)));
+    }
+    Ok(())
+}
 
-    // Compress the data
-    let mut lzss = Lzss::new();
-    let compressed_data = lzss.compress(&input_data)?;
+/// Runs `write` on stdout for "-", or else on a temporary file that is
+/// flushed, synced and renamed to `path` once `write` succeeds. An existing
+/// file is only replaced if `force` is set. If anything fails the temporary
+/// file is removed, so neither truncated output nor a stray temporary file
+/// is left behind, and whatever was at `path` is untouched.
+fn with_output<T>(path: &str, force: bool, write: impl FnOnce(&mut dyn Write) -> Result<T, LzssError>) -> Result<T, LzssError> {
+    if path == STDIO {
+        let mut output = io::stdout().lock();
+        let value = write(&mut output)?;
+        output.flush()?;
+        return Ok(value);
+    }
 
-    let compressed_size = compressed_data.len();
-    println!("Compressed: {} bytes -> {} bytes ({:.1}% of original)", 
-             input_size, compressed_size, 
-             (compressed_size as f64 / input_size as f64) * 100.0);
+    check_overwrite(path, force)?;
+    let temp = temp_path(path);
+    let result = (|| -> Result<T, LzssError> {
+        let mut output = OpenOptions::new().write(true).create_new(true).open(&temp)?;
+        let value = write(&mut output)?;
+        output.sync_all()?;
+        // Checked again in case something appeared at the path meanwhile
+        check_overwrite(path, force)?;
+        fs::rename(&temp, path)?;
+        Ok(value)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
 
-    // Write compressed data to output file
-    let mut output_file = File::create(&output_path)?;
-    output_file.write_all(&compressed_data)?;
-    output_file.flush()?;
+/// Writes `data` to a file, or to stdout for "-"
+fn write_output(path: &str, data: &[u8], force: bool) -> Result<(), LzssError> {
+    with_output(path, force, |output| Ok(output.write_all(data)?))
+}
 
-    println!("Compressed to {}", output_path.as_ref().display());
+/// Removes `input_file` after it was compressed or decompressed into
+/// `output_file`, for --delete. Stdin is left alone, and so is an input
+/// whose result only went to stdout, as with gzip -c, or that was
+/// overwritten by it.
+fn remove_input(input_file: &str, output_file: &str, options: &Options) -> io::Result<()> {
+    if input_file == STDIO || output_file == STDIO || Path::new(input_file) == Path::new(output_file) {
+        return Ok(());
+    }
+    fs::remove_file(input_file)?;
+    info!(options, "Removed {}", input_file);
     Ok(())
 }
 
-fn decompress_file<P: AsRef<Path>>(input_path: P, output_path: P) -> io::Result<()> {
-    // Read the entire compressed file into memory
-    let mut input_file = File::open(&input_path)?;
-    let mut compressed_data = Vec::new();
-    input_file.read_to_end(&mut compressed_data)?;
+/// How a path is shown in diagnostics
+fn display_name(path: &str) -> &str {
+    if path == STDIO { "<stdout>" } else { path }
+}
+
+/// Compresses `input_data` into the framed format with `header`, or if there
+/// is no header, into the checkpointed one with --checkpointed or else the
+/// classic one. Returns the number of bytes read and written.
+fn compress_file(input_data: &[u8], output_path: &str, header: Option<&FrameHeader>, options: &Options) -> Result<(u64, u64), LzssError> {
+    let input_size = input_data.len() as u64;
+    info!(options, "Reading file: {} bytes", input_size);
 
-    let compressed_size = compressed_data.len();
-    println!("Reading compressed file: {} bytes", compressed_size);
+    // Compress the data. The framed header needs the compressed length, so
+    // that format is compressed in memory first; the classic one streams.
+    let mut lzss = new_lzss(options)?;
+    let summary = match header {
+        Some(header) => {
+            #[cfg(feature = "parallel")]
+            let compressed_data = lzss
+                .with_threads(options.threads.unwrap_or(0))
+                .compress_framed_parallel(input_data, header, BLOCK_SIZE)?;
+            #[cfg(not(feature = "parallel"))]
+            let compressed_data = lzss.compress_framed_with_header(input_data, header)?;
+            write_output(output_path, &compressed_data, options.force)?;
+            CompressionSummary::new(input_size, compressed_data.len() as u64)
+        }
+        #[cfg(feature = "checkpointed")]
+        None if options.checkpointed => {
+            let compressed_data = lzss.compress_checkpointed(input_data, CHECKPOINT_INTERVAL)?;
+            write_output(output_path, &compressed_data, options.force)?;
+            CompressionSummary::new(input_size, compressed_data.len() as u64)
+        }
+        None => with_output(output_path, options.force, |output| lzss.compress_to_writer(input_data, output))?,
+    };
 
-    // Decompress the data
-    let mut lzss = Lzss::new();
-    let decompressed_data = lzss.decompress(&compressed_data)?;
+    info!(options, "Compressed: {}", summary);
+    info!(options, "Compressed to {}", display_name(output_path));
+    if options.stats {
+        print_stats(input_data, options)?;
+    }
+    Ok((summary.input_len, summary.output_len))
+}
 
-    let decompressed_size = decompressed_data.len();
-    println!("Decompressed: {} bytes -> {} bytes", compressed_size, decompressed_size);
+/// Prints the ratio compress_file() would achieve, without writing anything.
+/// Returns the input size and the projected compressed size.
+fn estimate_file(input_path: &str, input_data: &[u8], options: &Options) -> Result<(u64, u64), LzssError> {
+    let mut lzss = new_lzss(options)?;
+    let compressed_size = lzss.compressed_size(input_data)?;
 
-    // Write decompressed data to output file
-    let mut output_file = File::create(&output_path)?;
-    output_file.write_all(&decompressed_data)?;
-    output_file.flush()?;
+    let input_size = input_data.len() as u64;
+    writeln!(io::stdout(), "{}: {}", input_path, CompressionSummary::new(input_size, compressed_size))?;
+    if options.stats {
+        print_stats(input_data, options)?;
+    }
+    Ok((input_size, compressed_size))
+}
 
-    println!("Decompressed to {}", output_path.as_ref().display());
+/// Prints to stderr, for --stats, how `input_data` is encoded: the share
+/// of literals, the matches, and how their lengths are spread over
+/// power-of-two ranges. This takes a pass of its own, as the framed and
+/// parallel paths don't collect statistics, so it describes a single
+/// classic stream; a split into blocks differs slightly.
+fn print_stats(input_data: &[u8], options: &Options) -> Result<(), LzssError> {
+    let (_, stats) = new_lzss(options)?.compress_with_stats(input_data)?;
+    eprintln!("Literals: {} ({:.1}% of input)", stats.literals, stats.literal_fraction() * 100.0);
+    eprintln!("Matches: {} covering {} bytes", stats.matches, stats.matched_bytes);
+    eprintln!("Match length: average {:.1}, longest {}", stats.average_match_length(), stats.longest_match);
+    if stats.matches > 0 {
+        eprintln!("Match lengths: {}", length_ranges(&stats).join(", "));
+    }
     Ok(())
 }
+
+/// The share of matches in each range of lengths 2^k to 2^(k+1) - 1, as
+/// "4-7 31.2%", leaving out empty ranges.
+fn length_ranges(stats: &CompressionStats) -> Vec<String> {
+    let histogram = &stats.match_length_histogram;
+    let mut ranges = Vec::new();
+    let mut low = 1;
+    while low < histogram.len() {
+        let high = (low * 2 - 1).min(histogram.len() - 1);
+        let count: u64 = histogram[low..=high].iter().sum();
+        if count > 0 {
+            let share = count as f64 / stats.matches as f64 * 100.0;
+            if low == high {
+                ranges.push(format!("{} {:.1}%", low, share));
+            } else {
+                ranges.push(format!("{}-{} {:.1}%", low, high, share));
+            }
+        }
+        low *= 2;
+    }
+    ranges
+}
+
+/// Decompresses `compressed_data` from the framed format, or the classic one
+/// if `framed` is false, without writing the result anywhere, and prints
+/// OK if that works. Returns the compressed and decompressed sizes. With
+/// --checkpointed, damage is reported with where in the data it is.
+fn verify_file(input_path: &str, compressed_data: &[u8], framed: bool, options: &Options) -> Result<(u64, u64), LzssError> {
+    let mut lzss = new_lzss(options)?;
+    let decompressed_size = match framed {
+        true => lzss.decompressed_size_framed(compressed_data)?,
+        #[cfg(feature = "checkpointed")]
+        false if options.checkpointed => lzss.verify_checkpointed(compressed_data)?,
+        false => lzss.decompressed_size(compressed_data)?,
+    };
+
+    writeln!(io::stdout(), "{}: OK", input_path)?;
+    Ok((compressed_data.len() as u64, decompressed_size))
+}
+
+/// Compresses the files under `dir` into an archive at `output_path`, or
+/// else at the directory's name with `.lzss` added. --raw, --checkpointed
+/// and --estimate don't apply, and --delete leaves directories alone.
+/// Returns the number of bytes read and written.
+fn compress_dir(dir: &str, output_path: Option<&str>, options: &Options) -> Result<(u64, u64), LzssError> {
+    if options.raw || options.checkpointed || options.estimate {
+        return Err(LzssError::Io(io::Error::new(io::ErrorKind::InvalidInput,
+            "--raw, --checkpointed and --estimate don't apply to directories")));
+    }
+    let output_path = output_path.map_or_else(|| format!("{}.lzss", dir.trim_end_matches(['/', '\\'])), str::to_string);
+
+    let entries = ArchiveEntry::read_dir(dir)?;
+    let archive = new_lzss(options)?.compress_archive(&entries)?;
+    write_output(&output_path, &archive, options.force)?;
+
+    let input_size = entries.iter().map(|entry| entry.data.len() as u64).sum();
+    let summary = CompressionSummary::new(input_size, archive.len() as u64);
+    info!(options, "Archived {}: {}", dir, summary);
+    info!(options, "Archived to {}", display_name(&output_path));
+    Ok((summary.input_len, summary.output_len))
+}
+
+/// Recreates the files of the archive `archive` in the directory `dest`.
+/// Returns the number of bytes read and written.
+fn extract_file(archive: &[u8], dest: &str, options: &Options) -> Result<(u64, u64), LzssError> {
+    if dest == STDIO {
+        return Err(LzssError::Io(io::Error::new(io::ErrorKind::InvalidInput,
+            "an archive can only be extracted into a directory; use --output")));
+    }
+    let summary = new_lzss(options)?.extract_archive(archive, dest)?;
+
+    info!(options, "Extracted {} bytes -> {} bytes into {}", summary.output_len, summary.input_len, dest);
+    Ok((summary.output_len, summary.input_len))
+}
+
+/// Decompresses `compressed_data` from the framed format, the checkpointed
+/// one with --checkpointed, or else the classic one. Returns the number of
+/// bytes read and written.
+fn decompress_file(compressed_data: &[u8], output_path: &str, framed: bool, options: &Options) -> Result<(u64, u64), LzssError> {
+    let compressed_size = compressed_data.len() as u64;
+    info!(options, "Reading compressed file: {} bytes", compressed_size);
+
+    // Decompress the data
+    let mut lzss = new_lzss(options)?;
+    let (decompressed_size, header) = match framed {
+        true => {
+            // Concatenated framed streams decompress to their concatenation,
+            // as with gzip; the first one's header stands for all.
+            let header = Lzss::peek_frame_header(compressed_data)?;
+            let decompressed_data = lzss.decompress_framed(compressed_data)?;
+            write_output(output_path, &decompressed_data, options.force)?;
+            (decompressed_data.len() as u64, header)
+        }
+        #[cfg(feature = "checkpointed")]
+        false if options.checkpointed => {
+            let decompressed_data = lzss.decompress_checkpointed(compressed_data)?;
+            write_output(output_path, &decompressed_data, options.force)?;
+            (decompressed_data.len() as u64, FrameHeader::default())
+        }
+        false => {
+            let summary = with_output(output_path, options.force, |output| lzss.decompress_from_reader(compressed_data, output))?;
+            (summary.input_len, FrameHeader::default())
+        }
+    };
+
+    info!(options, "Decompressed: {} bytes -> {} bytes", compressed_size, decompressed_size);
+    if let Some(mtime) = header.mtime.filter(|_| output_path != STDIO && !options.no_time) {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(mtime);
+        File::options().write(true).open(output_path)?.set_modified(modified)?;
+    }
+
+    info!(options, "Decompressed to {}", display_name(output_path));
+    Ok((compressed_size, decompressed_size))
+}
+
+#[cfg(all(test, feature = "memmap2"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_input_compresses_as_read_input() {
+        let path = env::temp_dir().join(format!("lzss-mmap-{}", std::process::id()));
+        let data = b"a mapped file reads like any other, a mapped file ".repeat(5000);
+        fs::write(&path, &data).unwrap();
+
+        let input = read_input(path.to_str().unwrap()).unwrap();
+        assert!(matches!(input, Input::Mapped(_)));
+        let read = fs::read(&path).unwrap();
+        assert_eq!(Lzss::new().compress(&input).unwrap(), Lzss::new().compress(&read).unwrap());
+
+        let mut streamed = Vec::new();
+        Lzss::new().compress_to_writer(&input[..], &mut streamed).unwrap();
+        assert_eq!(streamed, lzss::compress(&data).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
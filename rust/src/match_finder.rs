@@ -0,0 +1,444 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+/// Finds, for the string about to be encoded, the longest earlier string
+/// in the ring buffer that it starts with.
+///
+/// The encoder calls insert() for each position r as its string becomes
+/// complete, reads best_match() right after, and calls remove() on a
+/// position just before the ring buffer overwrites it. A position is
+/// inserted at most once between removals. `text_buf` holds the ring buffer
+/// of N bytes followed by a copy of its first F - 1, so
+/// `text_buf[r..r + F]` is always the whole string at r.
+///
+/// best_match() may report any position whose string is in the finder, as
+/// long as the first `length` bytes there are the same as at r. A length of
+/// at most the threshold, including 0, means no match.
+///
+/// ```
+/// use lzss::{Lzss, MatchFinder};
+///
+/// /// Never finds anything, so every byte is sent uncoded.
+/// struct NoMatches;
+///
+/// impl MatchFinder for NoMatches {
+///     fn reset(&mut self, _n: usize, _f: usize) {}
+///     fn insert(&mut self, _r: usize, _text_buf: &[u8]) {}
+///     fn best_match(&self) -> (usize, usize) { (0, 0) }
+///     fn remove(&mut self, _p: usize) {}
+/// }
+///
+/// let data = b"abcabcabcabcabcabc";
+/// let compressed = Lzss::new().with_match_finder(NoMatches).compress(data)?;
+/// assert_eq!(compressed.len(), data.len() + 3); // one flags byte per 8 literals
+/// assert_eq!(Lzss::new().decompress(&compressed)?, data);
+/// # Ok::<(), lzss::LzssError>(())
+/// ```
+pub trait MatchFinder: Send {
+    /// Forgets every string, ready for a ring buffer of `n` bytes and
+    /// strings of `f` bytes. Called before each compression.
+    fn reset(&mut self, n: usize, f: usize);
+    /// Looks for the longest match for the string at r, then adds it.
+    fn insert(&mut self, r: usize, text_buf: &[u8]);
+    /// Position and length of the match found by the last insert()
+    fn best_match(&self) -> (usize, usize);
+    /// Removes the string at p.
+    fn remove(&mut self, p: usize);
+    /// A copy of this finder, with every string in it, for Lzss::clone().
+    /// The default, None, means the finder can't be copied.
+    fn clone_box(&self) -> Option<Box<dyn MatchFinder>> {
+        None
+    }
+}
+
+/// How the encoder looks for earlier occurrences of the string at r
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchStrategy {
+    /// Okumura's binary search trees, which always find the longest match.
+    ///
+    /// A run of one byte value doesn't make them degenerate into a list:
+    /// each string in the run matches the one before it in full, and a
+    /// full match takes over that node rather than hanging below it. So
+    /// the tree stays one node deep, and such input compresses in linear
+    /// time, at the best ratio the pair format allows.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = vec![0xAA; 1 << 20];
+    /// let mut lzss = Lzss::new();
+    /// let compressed = lzss.compress(&data)?;
+    /// // 17 bytes of code, a flags byte and 8 pairs, for every 8 * F bytes
+    /// assert!(compressed.len() * 10 < data.len());
+    /// assert_eq!(lzss.decompress(&compressed)?, data);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    #[default]
+    BinaryTree,
+    /// Chains of positions that share a hash of their first three bytes.
+    /// Only a bounded number of candidates is tried, so it can miss the
+    /// longest match, but its cost per byte doesn't depend on the data.
+    HashChain,
+}
+
+/// Number of binary search trees: one for the strings starting with each
+/// byte value
+const NUM_TREES: usize = 256;
+
+/// Type of the node indexes the trees keep: usize by default, or with the
+/// compact feature the smallest that holds every node, root and NIL.
+pub trait TreeIndex: Copy + Send + Sync + 'static {
+    /// Most nodes, roots and NIL included, the type can tell apart
+    const CAPACITY: usize;
+    /// `i`, which must be below CAPACITY
+    fn new(i: usize) -> Self;
+    fn get(self) -> usize;
+}
+
+macro_rules! tree_index {
+    ($($t:ty),*) => {$(
+        impl TreeIndex for $t {
+            const CAPACITY: usize = (<$t>::MAX as usize).saturating_add(1);
+
+            #[inline]
+            fn new(i: usize) -> Self {
+                debug_assert!(i < Self::CAPACITY);
+                i as $t
+            }
+
+            #[inline]
+            fn get(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+
+tree_index!(u16, u32, usize);
+
+/// Okumura's binary search trees, one for each first byte
+#[derive(Clone)]
+pub struct BstMatchFinder<I: TreeIndex = usize> {
+    /// size of ring buffer
+    n: usize,
+    /// upper limit for match_length
+    f: usize,
+    /// index for root of binary search trees
+    nil: usize,
+    /// of longest match. These are set by the insert() procedure.
+    match_position: usize,
+    match_length: usize,
+    /// most nodes compared for one string before giving up on it
+    max_chain: usize,
+    /// whether a node matching as long as the best so far, but nearer, takes its place
+    nearest: bool,
+    /// left & right children & parents -- These constitute binary search trees.
+    /// All three have a slot for each of the N nodes and one for NIL, which
+    /// absorbs the writes to a missing child's parent. rson also holds the
+    /// NUM_TREES roots after that; see root().
+    lson: Vec<I>,
+    rson: Vec<I>,
+    dad: Vec<I>,
+}
+
+impl BstMatchFinder {
+    pub fn new() -> Self {
+        Self::with_max_chain(usize::MAX)
+    }
+
+    /// Trees that compare at most `max_chain` nodes for each string. A
+    /// search cut short takes the longest match found so far, and the
+    /// string is left out of the trees, since it can't be placed without
+    /// going on. That bounds the time spent on each byte, even for input
+    /// that makes the trees deep, for a small loss of ratio. new() has no
+    /// limit.
+    pub fn with_max_chain(max_chain: usize) -> Self {
+        Self::with_index(max_chain)
+    }
+}
+
+impl<I: TreeIndex> BstMatchFinder<I> {
+    /// Like with_max_chain(), keeping node indexes as I. Only for windows
+    /// that need fewer than I::CAPACITY nodes; see tree_capacity().
+    pub(crate) fn with_index(max_chain: usize) -> Self {
+        Self {
+            n: 0,
+            f: 0,
+            nil: 0,
+            match_position: 0,
+            match_length: 0,
+            max_chain,
+            nearest: false,
+            lson: Vec::new(),
+            rson: Vec::new(),
+            dad: Vec::new(),
+        }
+    }
+}
+
+/// Number of node indexes the trees need for a ring buffer of `n` bytes:
+/// a node for each position, NIL, and the roots.
+pub(crate) fn tree_capacity(n: usize) -> usize {
+    n + 1 + NUM_TREES
+}
+
+/// The trees for a ring buffer of `n` bytes, with node indexes of the
+/// smallest type that holds them.
+#[cfg(feature = "compact")]
+pub(crate) fn tree_finder(n: usize, max_chain: usize, nearest: bool) -> Box<dyn MatchFinder> {
+    if tree_capacity(n) <= u16::CAPACITY {
+        Box::new(BstMatchFinder::<u16>::with_index(max_chain).with_nearest_matches(nearest))
+    } else {
+        Box::new(BstMatchFinder::<u32>::with_index(max_chain).with_nearest_matches(nearest))
+    }
+}
+
+/// The trees for a ring buffer of `n` bytes, with usize node indexes.
+#[cfg(not(feature = "compact"))]
+pub(crate) fn tree_finder(_n: usize, max_chain: usize, nearest: bool) -> Box<dyn MatchFinder> {
+    Box::new(BstMatchFinder::with_max_chain(max_chain).with_nearest_matches(nearest))
+}
+
+impl<I: TreeIndex> BstMatchFinder<I> {
+    /// Of the nodes compared that match as long, reports the nearest to r
+    /// rather than the first. The tree a string is compared against
+    /// doesn't change, only which of equally long matches a pair refers
+    /// to, so the output is the same size either way. Off by default, as
+    /// in LZSS.C, whose output it then reproduces.
+    ///
+    /// Only the nodes on the way down are compared, so an equally long
+    /// match elsewhere in the tree, nearer still, can go unnoticed. A
+    /// string matched in full, the most common case in repetitive data,
+    /// is always the nearest, since the newer string takes over its node.
+    pub fn with_nearest_matches(mut self, nearest: bool) -> Self {
+        self.nearest = nearest;
+        self
+    }
+
+    /// Index in rson of the root of the tree for strings starting with `c`.
+    /// A root only ever has a right child, and never a parent, so lson and
+    /// dad need no slots for the roots.
+    fn root(&self, c: u8) -> usize {
+        self.nil + 1 + c as usize
+    }
+}
+
+impl Default for BstMatchFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: TreeIndex> MatchFinder for BstMatchFinder<I> {
+    /// Initialize trees
+    fn reset(&mut self, n: usize, f: usize) {
+        // For i = 0 to N - 1, rson[i] and lson[i] will be the right and
+        // left children of node i.  These nodes need not be initialized.
+        // Also, dad[i] is the parent of node i.  These are initialized to
+        // NIL (= N), which stands for 'not used.'
+        // For i = 0 to 255, rson[N + i + 1] is the root of the tree
+        // for strings that begin with character i.  These are initialized
+        // to NIL.  Note there are 256 trees.
+
+        assert!(tree_capacity(n) <= I::CAPACITY, "window too large for the tree index type");
+        if self.n != n {
+            self.lson = vec![I::new(n); n + 1];
+            self.rson = vec![I::new(n); tree_capacity(n)];
+            self.dad = vec![I::new(n); n + 1];
+        }
+        self.n = n;
+        self.f = f;
+        self.nil = n;
+        self.match_position = 0;
+        self.match_length = 0;
+
+        for c in 0..=u8::MAX {
+            let root = self.root(c);
+            self.rson[root] = I::new(self.nil);
+        }
+        for i in 0..self.n {
+            self.dad[i] = I::new(self.nil);
+        }
+    }
+
+    /// Inserts string of length F, text_buf[r..r+F-1], into one of the
+    /// trees (text_buf[r]'th tree) and returns the longest-match position
+    /// and length via the global variables match_position and match_length.
+    /// If match_length = F, then removes the old node in favor of the new
+    /// one, because the old one will be deleted sooner.
+    /// Note r plays double role, as tree node and position in buffer.
+    fn insert(&mut self, r: usize, text_buf: &[u8]) {
+        debug_assert!(r < self.n && text_buf.len() >= self.n + self.f - 1);
+        let mut cmp = 1i32;
+        let key = r;
+        let mut p = self.root(text_buf[key]);
+        
+        self.rson[r] = I::new(self.nil);
+        self.lson[r] = I::new(self.nil);
+        self.match_length = 0;
+        let mut compared = 0;
+        
+        loop {
+            if cmp >= 0 {
+                if self.rson[p].get() != self.nil {
+                    p = self.rson[p].get();
+                } else {
+                    self.rson[p] = I::new(r);
+                    self.dad[r] = I::new(p);
+                    return;
+                }
+            } else {
+                if self.lson[p].get() != self.nil {
+                    p = self.lson[p].get();
+                } else {
+                    self.lson[p] = I::new(r);
+                    self.dad[r] = I::new(p);
+                    return;
+                }
+            }
+            
+            if compared == self.max_chain {
+                // Not linked in, so remove() will pass over it
+                self.dad[r] = I::new(self.nil);
+                return;
+            }
+            compared += 1;
+
+            // p is a node now, not a root, so with i below F both indexes
+            // stay within the N + F - 1 bytes of text_buf.
+            debug_assert!(p < self.n);
+            let mut i = 1;
+            while i < self.f {
+                cmp = text_buf[key + i] as i32 - text_buf[p + i] as i32;
+                if cmp != 0 {
+                    break;
+                }
+                i += 1;
+            }
+            
+            let nearer = |p: usize, q: usize| (key + self.n - p) & (self.n - 1) < (key + self.n - q) & (self.n - 1);
+            if i > self.match_length || (self.nearest && i == self.match_length && nearer(p, self.match_position)) {
+                self.match_position = p;
+                self.match_length = i;
+                if self.match_length >= self.f {
+                    break;
+                }
+            }
+        }
+        
+        self.dad[r] = self.dad[p];
+        self.lson[r] = self.lson[p];
+        self.rson[r] = self.rson[p];
+        self.dad[self.lson[p].get()] = I::new(r);
+        self.dad[self.rson[p].get()] = I::new(r);
+        
+        // A root's only child is its right one, so lson is never indexed
+        // past the nodes and NIL.
+        debug_assert!(self.dad[p].get() <= self.nil || self.rson[self.dad[p].get()].get() == p);
+        if self.rson[self.dad[p].get()].get() == p {
+            self.rson[self.dad[p].get()] = I::new(r);
+        } else {
+            self.lson[self.dad[p].get()] = I::new(r);
+        }
+        
+        self.dad[p] = I::new(self.nil); // remove p
+    }
+
+    fn best_match(&self) -> (usize, usize) {
+        (self.match_position, self.match_length)
+    }
+
+    /// Deletes node p from tree
+    fn remove(&mut self, p: usize) {
+        if self.dad[p].get() == self.nil {
+            return; // not in tree
+        }
+        
+        let q = if self.rson[p].get() == self.nil {
+            self.lson[p].get()
+        } else if self.lson[p].get() == self.nil {
+            self.rson[p].get()
+        } else {
+            let mut q = self.lson[p].get();
+            if self.rson[q].get() != self.nil {
+                while self.rson[q].get() != self.nil {
+                    q = self.rson[q].get();
+                }
+                self.rson[self.dad[q].get()] = self.lson[q];
+                self.dad[self.lson[q].get()] = self.dad[q];
+                self.lson[q] = self.lson[p];
+                self.dad[self.lson[p].get()] = I::new(q);
+            }
+            self.rson[q] = self.rson[p];
+            self.dad[self.rson[p].get()] = I::new(q);
+            q
+        };
+        
+        self.dad[q] = self.dad[p];
+        debug_assert!(self.dad[p].get() <= self.nil || self.rson[self.dad[p].get()].get() == p);
+        if self.rson[self.dad[p].get()].get() == p {
+            self.rson[self.dad[p].get()] = I::new(q);
+        } else {
+            self.lson[self.dad[p].get()] = I::new(q);
+        }
+        self.dad[p] = I::new(self.nil);
+    }
+
+    fn clone_box(&self) -> Option<Box<dyn MatchFinder>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ring buffer of `n` bytes where position p starts with byte p % 256,
+    /// followed by the copy of its first F - 1 bytes.
+    fn ring(n: usize, f: usize) -> Vec<u8> {
+        let mut text_buf: Vec<u8> = (0..n).map(|p| (p % 256) as u8 ^ (p / 256 % 3) as u8).collect();
+        text_buf.extend_from_within(..f - 1);
+        text_buf
+    }
+
+    fn fill_and_empty_every_tree<I: TreeIndex>(n: usize) {
+        let f = 18;
+        let text_buf = ring(n, f);
+        let mut finder = BstMatchFinder::<I>::with_index(usize::MAX);
+        finder.reset(n, f);
+        for r in 0..n {
+            finder.insert(r, &text_buf);
+            let (position, length) = finder.best_match();
+            if length > 0 {
+                assert!(position < n);
+                assert_eq!(text_buf[position..position + length], text_buf[r..r + length]);
+            }
+        }
+        for c in 0..=u8::MAX {
+            assert_ne!(finder.rson[finder.root(c)].get(), finder.nil, "tree {c} is empty");
+        }
+        assert_eq!(finder.rson.len(), finder.root(u8::MAX) + 1);
+
+        for p in 0..n {
+            finder.remove(p);
+        }
+        for c in 0..=u8::MAX {
+            assert_eq!(finder.rson[finder.root(c)].get(), finder.nil, "tree {c} is not empty");
+        }
+        assert!(finder.dad[..n].iter().all(|d| d.get() == finder.nil));
+    }
+
+    #[test]
+    fn all_256_trees_fill_and_empty() {
+        for n in [256, 2048, 1 << 15] {
+            fill_and_empty_every_tree::<usize>(n);
+            fill_and_empty_every_tree::<u32>(n);
+            fill_and_empty_every_tree::<u16>(n);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "window too large")]
+    fn index_type_too_small_for_the_window_is_refused() {
+        BstMatchFinder::<u16>::with_index(usize::MAX).reset(1 << 16, 18);
+    }
+}
@@ -0,0 +1,44 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Cost of an unencoded letter in bits: its flag and the byte itself
+const LITERAL_BITS: u64 = 9;
+
+/// Chooses the units that encode the input in the fewest bits, given the
+/// longest match the match finder found at each position of it.
+///
+/// Any prefix of a match is a match too, so at each position the choice is
+/// a literal or a pair of any length from `threshold + 1` up to the longest
+/// one. Working back from the end, the cheapest way to encode the rest of
+/// the input from each position is the cheapest of those choices plus the
+/// cheapest way on from where it leads. A pair costs its flag and
+/// `pair_len` bytes.
+///
+/// Returns the length of the unit to send at each position, 1 for a
+/// literal. Only the entries at positions the plan actually reaches are
+/// meaningful.
+pub(crate) fn plan(longest: &[u16], threshold: usize, pair_len: usize) -> Vec<u16> {
+    let n = longest.len();
+    let pair_bits = 1 + 8 * pair_len as u64;
+    let mut cost = vec![0u64; n + 1];
+    let mut choice = vec![1u16; n];
+
+    for i in (0..n).rev() {
+        let mut best = cost[i + 1] + LITERAL_BITS;
+        let mut best_length = 1;
+        // Longer first, so a tie goes to the longer match, as the greedy
+        // parse would take it.
+        let longest = (longest[i] as usize).min(n - i);
+        for length in (threshold + 1..=longest).rev() {
+            let c = cost[i + length] + pair_bits;
+            if c < best {
+                best = c;
+                best_length = length;
+            }
+        }
+        cost[i] = best;
+        choice[i] = best_length as u16;
+    }
+
+    choice
+}
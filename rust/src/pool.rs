@@ -0,0 +1,132 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use crate::lzss_stream::Lzss;
+
+/// Keeps `Lzss` instances around for reuse, so compressing many small
+/// payloads doesn't allocate the ring buffer and trees each time:
+///
+/// ```
+/// use lzss::LzssPool;
+///
+/// let pool = LzssPool::new();
+/// let compressed = pool.get().compress(b"payload payload payload")?;
+/// assert_eq!(pool.get().decompress(&compressed)?, b"payload payload payload");
+/// assert_eq!(pool.idle(), 1); // both calls used the same instance
+/// # Ok::<(), lzss::LzssError>(())
+/// ```
+///
+/// The pool can be shared between threads; it grows to the largest number
+/// of instances ever in use at once.
+pub struct LzssPool {
+    idle: Mutex<Vec<Lzss>>,
+    make: Box<dyn Fn() -> Lzss + Send + Sync>,
+}
+
+impl LzssPool {
+    /// A pool of instances with the default parameters
+    pub fn new() -> Self {
+        Self::with_factory(Lzss::new)
+    }
+
+    /// A pool that calls `make` whenever it needs another instance, for
+    /// parameters other than the defaults.
+    pub fn with_factory<F: Fn() -> Lzss + Send + Sync + 'static>(make: F) -> Self {
+        Self {
+            idle: Mutex::new(Vec::new()),
+            make: Box::new(make),
+        }
+    }
+
+    /// Takes an idle instance, or makes one if there are none. It goes back
+    /// to the pool, reset, when the guard is dropped.
+    pub fn get(&self) -> PooledLzss<'_> {
+        let lzss = self.lock().pop().unwrap_or_else(|| (self.make)());
+        PooledLzss { pool: self, lzss: Some(lzss) }
+    }
+
+    /// Number of instances waiting to be handed out
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Lzss>> {
+        // A panic while holding the lock can't leave the Vec inconsistent.
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for LzssPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An `Lzss` borrowed from an `LzssPool`
+pub struct PooledLzss<'a> {
+    pool: &'a LzssPool,
+    /// only None while being dropped
+    lzss: Option<Lzss>,
+}
+
+impl Deref for PooledLzss<'_> {
+    type Target = Lzss;
+
+    fn deref(&self) -> &Lzss {
+        self.lzss.as_ref().expect("present until dropped")
+    }
+}
+
+impl DerefMut for PooledLzss<'_> {
+    fn deref_mut(&mut self) -> &mut Lzss {
+        self.lzss.as_mut().expect("present until dropped")
+    }
+}
+
+impl Drop for PooledLzss<'_> {
+    fn drop(&mut self) {
+        if let Some(mut lzss) = self.lzss.take() {
+            lzss.reset();
+            self.pool.lock().push(lzss);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn concurrent_use_reuses_instances() {
+        const THREADS: usize = 8;
+        let made = Arc::new(AtomicUsize::new(0));
+        let pool = LzssPool::with_factory({
+            let made = Arc::clone(&made);
+            move || {
+                made.fetch_add(1, Ordering::Relaxed);
+                Lzss::new()
+            }
+        });
+
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let pool = &pool;
+                scope.spawn(move || {
+                    for i in 0..200 {
+                        let payload = format!("request {i} from thread {t}, request {i} from thread {t}");
+                        let compressed = pool.get().compress(payload.as_bytes()).unwrap();
+                        assert_eq!(compressed, crate::compress(payload.as_bytes()).unwrap());
+                        assert_eq!(pool.get().decompress(&compressed).unwrap(), payload.as_bytes());
+                    }
+                });
+            }
+        });
+
+        let made = made.load(Ordering::Relaxed);
+        assert!((1..=THREADS).contains(&made), "{made} instances made");
+        assert_eq!(pool.idle(), made);
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::vec::Vec;
+
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+
+impl Lzss {
+    /// Compresses a batch of records as one stream, so that later records
+    /// can match earlier ones, as small records of one kind mostly do. The
+    /// records are fed to the encoder one after another, without the window
+    /// being reset between them, and the stream decompresses like any
+    /// other to the records joined together.
+    ///
+    /// No unit of the stream spans two records, so each record starts at a
+    /// unit of its own. Returns the stream with the offset of each record's
+    /// first unit in it, or of the flags in front when that unit opens a
+    /// code block; decompress_records() needs them to split the data up
+    /// again, so store them alongside. An empty record starts where the
+    /// next one does. Optimal parsing, if set, isn't used.
+    ///
+    /// Any record can only be decompressed together with those before it,
+    /// which its matches may refer to.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let records: Vec<Vec<u8>> = (0..50)
+    ///     .map(|i| format!(r#"{{"id":{},"status":"active","region":"eu-west-1"}}"#, i).into_bytes())
+    ///     .collect();
+    /// let records: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+    ///
+    /// let mut lzss = Lzss::new();
+    /// let (compressed, offsets) = lzss.compress_records(&records);
+    /// let separately: usize = records.iter().map(|r| lzss.compress(r).map(|c| c.len())).sum::<Result<_, _>>()?;
+    /// assert!(compressed.len() * 3 < separately);
+    ///
+    /// assert_eq!(lzss.decompress_records(&compressed, &offsets)?, records);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn compress_records(&mut self, records: &[&[u8]]) -> (Vec<u8>, Vec<usize>) {
+        let mut starts = Vec::with_capacity(records.len());
+        let mut start = 0;
+        for record in records {
+            starts.push(start);
+            start += record.len();
+        }
+
+        let mut output = Vec::new();
+        self.encode_begin();
+        self.start_units_at(&starts);
+        for record in records {
+            self.encode_update(record, &mut output).expect("encoding into a Vec can't fail");
+        }
+        self.encode_finish(&mut output).expect("encoding into a Vec can't fail");
+
+        (output, self.take_unit_offsets())
+    }
+
+    /// Reverse of compress_records(), given the offsets it returned. Fails
+    /// with `LzssError::InvalidBlockIndex` if they don't fit the stream:
+    /// they must start at 0 and never decrease or pass its end, and with no
+    /// offsets the stream must be empty. Offsets that fit but are not the
+    /// ones compress_records() returned split the data in the wrong places.
+    pub fn decompress_records(&mut self, buffer: &[u8], offsets: &[usize]) -> Result<Vec<Vec<u8>>, LzssError> {
+        if offsets.first().map_or(!buffer.is_empty(), |&first| first != 0) {
+            return Err(LzssError::InvalidBlockIndex);
+        }
+
+        // Decoding up to a record's first unit gives the data before it
+        let mut data = Vec::new();
+        let mut starts = Vec::with_capacity(offsets.len());
+        let mut decoded = 0;
+        self.decode_begin();
+        for &offset in offsets {
+            let code = buffer.get(decoded..offset).ok_or(LzssError::InvalidBlockIndex)?;
+            self.decode_update(code, &mut data)?;
+            starts.push(data.len());
+            decoded = offset;
+        }
+        self.decode_update(&buffer[decoded..], &mut data)?;
+        self.decode_finish()?;
+
+        let mut records = Vec::with_capacity(offsets.len());
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(data.len());
+            records.push(data[start..end].to_vec());
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_offset_is_where_its_record_starts() {
+        let fields = ["alpha", "", "beta", "alphabet", "", "", "gamma ray", "alpha beta gamma"];
+        let records: Vec<Vec<u8>> = (0..300).map(|i| fields[i % fields.len()].repeat(i % 5).into_bytes()).collect();
+        let records: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+
+        for lzss in [Lzss::new(), Lzss::new().with_lazy_matching(true), Lzss::new().with_flag_bits(16).unwrap()] {
+            for records in [&records[..], &records[..1], &records[1..2], &[]] {
+                let mut lzss = lzss.clone();
+                let (compressed, offsets) = lzss.compress_records(records);
+                assert_eq!(offsets.len(), records.len());
+                assert_eq!(lzss.decompress(&compressed).unwrap(), records.concat());
+
+                // Decoding the code before an offset gives exactly the
+                // records before it
+                for (i, &offset) in offsets.iter().enumerate() {
+                    lzss.decode_begin();
+                    let mut data = Vec::new();
+                    lzss.decode_update(&compressed[..offset], &mut data).unwrap();
+                    assert_eq!(data, records[..i].concat(), "record {i} at {offset}");
+                }
+                assert_eq!(lzss.decompress_records(&compressed, &offsets).unwrap(), records);
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_that_dont_fit_are_rejected() {
+        let records: [&[u8]; 3] = [b"one", b"two", b"three"];
+        let mut lzss = Lzss::new();
+        let (compressed, offsets) = lzss.compress_records(&records);
+
+        for offsets in [&[1, offsets[1], offsets[2]][..], &[0, offsets[2], offsets[1]], &[0, compressed.len() + 1], &[]] {
+            assert!(matches!(lzss.decompress_records(&compressed, offsets), Err(LzssError::InvalidBlockIndex)), "{offsets:?}");
+        }
+        assert_eq!(lzss.decompress_records(&[], &[]).unwrap(), Vec::<Vec<u8>>::new());
+    }
+}
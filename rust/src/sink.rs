@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+
+use crate::error::LzssError;
+
+/// Destination for encoded or decoded bytes. With the `std` feature every
+/// `io::Write` is one; without it, `Vec<u8>` and `SliceSink` are.
+pub(crate) trait Sink {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Sink for W {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError> {
+        self.write_all(data)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Sink for Vec<u8> {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Writes into a caller-supplied buffer, failing once it is full.
+pub(crate) struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Number of bytes written so far
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Sink for SliceSink<'_> {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError> {
+        let end = self.len + data.len();
+        if end > self.buf.len() {
+            return Err(LzssError::BufferTooSmall);
+        }
+        self.buf[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Throws the bytes away and only counts them. The count is a u64, since
+/// the bytes are never held and may add up to more than a 32-bit usize.
+#[derive(Default)]
+pub(crate) struct CountingSink {
+    len: u64,
+}
+
+impl CountingSink {
+    /// Number of bytes written so far
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl Sink for CountingSink {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError> {
+        self.len += data.len() as u64;
+        Ok(())
+    }
+}
+
+/// Collects into a `Vec`, failing once more than `limit` bytes arrive. The
+/// `Vec` never grows past `limit`, so a hostile stream can't make it
+/// allocate more than that either.
+pub(crate) struct LimitedSink {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl LimitedSink {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self { buf: Vec::new(), limit }
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Sink for LimitedSink {
+    fn put(&mut self, data: &[u8]) -> Result<(), LzssError> {
+        let end = self.buf.len() + data.len();
+        if end > self.limit {
+            return Err(LzssError::OutputLimitExceeded);
+        }
+        if end > self.buf.capacity() {
+            // Keep doubling, as extend would, but stop at the limit
+            let capacity = (self.buf.capacity() * 2).clamp(end, self.limit);
+            self.buf.reserve_exact(capacity - self.buf.len());
+        }
+        self.buf.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limited_sink_never_allocates_past_its_limit() {
+        let mut sink = LimitedSink::new(1000);
+        for _ in 0..99 {
+            sink.put(&[7; 10]).unwrap();
+        }
+        sink.put(&[7; 10]).unwrap();
+        assert!(matches!(sink.put(&[7]), Err(LzssError::OutputLimitExceeded)));
+        let buf = sink.into_inner();
+        assert_eq!(buf.len(), 1000);
+        assert_eq!(buf.capacity(), 1000);
+    }
+}
@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// What the encoder did with its input, as reported by compress_with_stats().
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressionStats {
+    /// number of bytes sent uncoded
+    pub literals: u64,
+    /// number of position-and-length pairs sent
+    pub matches: u64,
+    /// number of input bytes covered by those pairs
+    pub matched_bytes: u64,
+    /// longest match sent
+    pub longest_match: usize,
+    /// number of pairs sent of each length, indexed by the length, up to
+    /// the longest. Matches piling up at the top suggest a larger
+    /// lookahead would help.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let data = b"one fish two fish red fish blue fish".repeat(50);
+    /// let (_, stats) = Lzss::new().compress_with_stats(&data)?;
+    /// let histogram = &stats.match_length_histogram;
+    /// assert_eq!(histogram.len(), stats.longest_match + 1);
+    /// assert_eq!(histogram.iter().sum::<u64>(), stats.matches);
+    /// let covered: u64 = histogram.iter().enumerate().map(|(length, &count)| length as u64 * count).sum();
+    /// assert_eq!(covered, stats.matched_bytes);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub match_length_histogram: Vec<u64>,
+    /// whether the compressed output came out larger than the input, as
+    /// happens when little of it matches; see literal_fraction()
+    pub expanded: bool,
+}
+
+impl CompressionStats {
+    /// Average number of bytes covered by a position-and-length pair, or 0
+    /// if none were sent.
+    pub fn average_match_length(&self) -> f64 {
+        if self.matches == 0 {
+            0.0
+        } else {
+            self.matched_bytes as f64 / self.matches as f64
+        }
+    }
+
+    /// Fraction of the input sent as uncoded letters, from 0.0 to 1.0, or
+    /// 0 for empty input. Each letter costs a flag bit on top of its byte,
+    /// so near 1.0 the data is incompressible, and an `expanded` output is
+    /// expected rather than a sign of trouble.
+    ///
+    /// ```
+    /// use lzss::Lzss;
+    ///
+    /// let mut x = 1u32;
+    /// let noise: Vec<u8> = (0..10_000).map(|_| {
+    ///     x ^= x << 13;
+    ///     x ^= x >> 17;
+    ///     x ^= x << 5;
+    ///     x as u8
+    /// }).collect();
+    /// let (_, stats) = Lzss::new().compress_with_stats(&noise)?;
+    /// assert!(stats.expanded);
+    /// assert!(stats.literal_fraction() > 0.9);
+    ///
+    /// let (_, stats) = Lzss::new().compress_with_stats(&b"tick tock ".repeat(1000))?;
+    /// assert!(!stats.expanded);
+    /// assert!(stats.literal_fraction() < 0.01);
+    /// # Ok::<(), lzss::LzssError>(())
+    /// ```
+    pub fn literal_fraction(&self) -> f64 {
+        let total = self.literals + self.matched_bytes;
+        if total == 0 {
+            0.0
+        } else {
+            self.literals as f64 / total as f64
+        }
+    }
+}
+
+/// Sizes before and after compression, printed the same way everywhere:
+///
+/// ```
+/// use lzss::CompressionSummary;
+///
+/// let summary = CompressionSummary::new(2000, 500);
+/// assert_eq!(summary.to_string(), "2000 bytes -> 500 bytes (25.0% of original)");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionSummary {
+    /// number of bytes before compression
+    pub input_len: u64,
+    /// number of bytes after compression
+    pub output_len: u64,
+}
+
+impl CompressionSummary {
+    pub fn new(input_len: u64, output_len: u64) -> Self {
+        Self { input_len, output_len }
+    }
+
+    /// Output size as a fraction of the input size, so below 1.0 means the
+    /// data shrank. Empty input compresses to empty output, which counts as
+    /// 1.0 rather than NaN.
+    pub fn ratio(&self) -> f64 {
+        if self.input_len == 0 {
+            1.0
+        } else {
+            self.output_len as f64 / self.input_len as f64
+        }
+    }
+}
+
+impl fmt::Display for CompressionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes -> {} bytes ({:.1}% of original)", self.input_len, self.output_len, self.ratio() * 100.0)
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+use crate::lzss_stream::Lzss;
+use crate::token::Token;
+
+/// Runs the encoder over an input one unit at a time, handing control back
+/// after each, so the state in between can be looked at. Meant for
+/// visualizing how LZSS works:
+///
+/// ```
+/// use lzss::{Lzss, StepEncoder};
+///
+/// let data = b"abcabcabcd";
+/// let mut steps = StepEncoder::new(data);
+/// while let Some(token) = steps.step() {
+///     println!("{:?} after {:?}", token, steps.lzss().window_snapshot());
+/// }
+///
+/// let (compressed, tokens) = Lzss::new().compress_with_tokens(data)?;
+/// assert_eq!(steps.into_output(), compressed);
+/// assert_eq!(StepEncoder::new(data).collect::<Vec<_>>(), tokens);
+/// # Ok::<(), lzss::LzssError>(())
+/// ```
+#[derive(Clone)]
+pub struct StepEncoder<'a> {
+    lzss: Lzss,
+    input: &'a [u8],
+    /// bytes of input fed to the encoder so far
+    consumed: usize,
+    /// units sent that step() hasn't returned yet; lazy matching can send
+    /// two at once
+    ready: VecDeque<Token>,
+    /// the compressed stream, up to the last full code block
+    output: Vec<u8>,
+    /// whether the input has run out and the final code block is in output
+    finished: bool,
+}
+
+impl<'a> StepEncoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_lzss(input, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the encoding.
+    pub fn with_lzss(input: &'a [u8], mut lzss: Lzss) -> Self {
+        lzss.encode_begin();
+        lzss.record_tokens();
+        Self {
+            lzss,
+            input,
+            consumed: 0,
+            ready: VecDeque::new(),
+            output: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Encodes as far as the next unit and returns it, or None once the
+    /// whole input is encoded. The units come out in the order
+    /// compress_with_tokens() reports them.
+    pub fn step(&mut self) -> Option<Token> {
+        while self.ready.is_empty() && !self.finished {
+            // Writing to a Vec can't fail, so neither can the encoder.
+            if let Some(&c) = self.input.get(self.consumed) {
+                self.lzss.encode_update(&[c], &mut self.output).expect("writing to a Vec can't fail");
+                self.consumed += 1;
+            } else if !self.lzss.encode_tail_unit(&mut self.output).expect("writing to a Vec can't fail") {
+                self.lzss.encode_finish(&mut self.output).expect("writing to a Vec can't fail");
+                self.finished = true;
+            }
+            self.ready.extend(self.lzss.take_tokens());
+        }
+        self.ready.pop_front()
+    }
+
+    /// The encoder, for looking at its state, such as window_snapshot()
+    pub fn lzss(&self) -> &Lzss {
+        &self.lzss
+    }
+
+    /// The longest match the encoder found for the string the last unit
+    /// was chosen at, or None if it found none longer than the threshold.
+    /// With lazy matching, this can differ from the unit step() returned.
+    pub fn current_match(&self) -> Option<Token> {
+        self.lzss.current_match()
+    }
+
+    /// Number of input bytes the encoder has read, which runs up to F
+    /// bytes ahead of what has been encoded.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// The compressed stream so far. Units are written out in code blocks
+    /// of eight or sixteen, so the latest few only appear once their block is full or
+    /// the input has ended.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// The compressed stream, the same as compress() produces once step()
+    /// has returned None.
+    pub fn into_output(self) -> Vec<u8> {
+        self.output
+    }
+}
+
+impl Iterator for StepEncoder<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.step()
+    }
+}
@@ -0,0 +1,27 @@
+/// One unit of a compressed stream, as reported by compress_with_tokens().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Token {
+    /// a byte sent uncoded
+    Literal(u8),
+    /// a copy of `length` bytes starting `distance` bytes back
+    Match { distance: usize, length: usize },
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::Lzss;
+
+    #[test]
+    fn tokens_round_trip_through_json() {
+        let data = b"one fish two fish red fish blue fish".repeat(10);
+        let (_, tokens) = Lzss::new().compress_with_tokens(&data).unwrap();
+        assert!(tokens.iter().any(|token| matches!(token, Token::Match { .. })));
+
+        let json = serde_json::to_string(&tokens).unwrap();
+        assert!(json.starts_with(r#"[{"Literal":111}"#));
+        let back: Vec<Token> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, tokens);
+    }
+}
@@ -0,0 +1,45 @@
+//! JavaScript bindings, for the `wasm` feature. Build them without the
+//! `std` feature, which keeps file and thread code out of the module:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/lzss.wasm
+//! ```
+//!
+//! `pkg/lzss.js` then exports `compress()` and `decompress()`, taking and
+//! returning `Uint8Array`s.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+/// Compresses `data` with the classic parameters, as the crate's compress().
+#[wasm_bindgen]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    crate::compress(data).expect("compressing into a Vec can't fail")
+}
+
+/// Decompresses a stream from compress(). Corrupt or truncated data throws
+/// an exception with the LzssError's message.
+#[wasm_bindgen]
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    crate::decompress(data).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // JsValue only exists on wasm targets, so the error path can't be
+    // exercised natively.
+    #[test]
+    fn round_trips() {
+        let data = b"hello hello hello, wasm".repeat(20);
+        let compressed = compress(&data);
+        assert_eq!(compressed, crate::compress(&data).unwrap());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+}
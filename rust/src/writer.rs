@@ -0,0 +1,103 @@
+use std::io::{self, Write};
+
+use crate::error::LzssError;
+use crate::lzss_stream::Lzss;
+
+/// Accepts uncompressed data through `Write`, in pieces of any size, and
+/// writes its compressed form to an inner writer as code blocks fill up,
+/// so content can be compressed as it is built:
+///
+/// ```
+/// use std::io::Write;
+/// use lzss::{Lzss, LzssWriter};
+///
+/// let mut writer = LzssWriter::new(Vec::new());
+/// for i in 0..100 {
+///     write!(writer, "line {} of the report\n", i)?;
+/// }
+/// let compressed = writer.finish()?;
+///
+/// let text: String = (0..100).map(|i| format!("line {} of the report\n", i)).collect();
+/// assert_eq!(compressed, Lzss::new().compress(text.as_bytes())?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// However the data is split up, the output is that of compress() on all
+/// of it, apart from optimal parsing, which needs the whole input and is
+/// ignored. Units only go out in whole code blocks, so flush() can't
+/// push out the last few without ending the stream; it only flushes the
+/// inner writer.
+///
+/// Call finish() once all data is written, to write out the final code
+/// block and get the inner writer back. Dropping an unfinished LzssWriter
+/// does the same, but has to ignore any error doing so:
+///
+/// ```
+/// use std::io::Write;
+/// use lzss::{Lzss, LzssWriter};
+///
+/// let data = b"a byte at a time, a byte at a time".repeat(50);
+/// let mut compressed = Vec::new();
+/// {
+///     let mut writer = LzssWriter::new(&mut compressed);
+///     for byte in &data {
+///         writer.write_all(&[*byte])?;
+///     }
+/// }
+/// assert_eq!(Lzss::new().decompress(&compressed)?, data);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LzssWriter<W: Write> {
+    /// only None once finish() has taken it
+    inner: Option<W>,
+    lzss: Lzss,
+}
+
+impl<W: Write> LzssWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_lzss(inner, Lzss::new())
+    }
+
+    /// Uses `lzss`, and whatever parameters it was built with, for the encoding.
+    pub fn with_lzss(inner: W, mut lzss: Lzss) -> Self {
+        lzss.encode_begin();
+        Self { inner: Some(inner), lzss }
+    }
+
+    /// The writer the compressed data goes to.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("present until finished")
+    }
+
+    /// Writes out the final code block, flushes the inner writer and
+    /// returns it.
+    pub fn finish(mut self) -> Result<W, LzssError> {
+        let mut inner = self.inner.take().expect("present until finished");
+        self.lzss.encode_finish(&mut inner)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for LzssWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let inner = self.inner.as_mut().expect("present until finished");
+        self.lzss.encode_update(buf, inner)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().expect("present until finished").flush()
+    }
+}
+
+impl<W: Write> Drop for LzssWriter<W> {
+    fn drop(&mut self) {
+        if let Some(mut inner) = self.inner.take() {
+            // There is no one to report an error to; finish() is for that.
+            if self.lzss.encode_finish(&mut inner).is_ok() {
+                let _ = inner.flush();
+            }
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! Runs the lzss binary on files in a scratch directory and checks its
+//! output, diagnostics and exit status.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// A fresh, empty directory for the test `name`
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lzss-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the binary in `dir` with `args`, feeding it `stdin`.
+fn lzss(dir: &Path, args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lzss"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Fed from a thread, so a child that writes as it reads can't block on
+    // a full stdout pipe while this waits for it to take more input.
+    let mut input = child.stdin.take().unwrap();
+    let stdin = stdin.to_vec();
+    let feeder = thread::spawn(move || {
+        // The child may exit without reading it all
+        let _ = input.write_all(&stdin);
+    });
+    let output = child.wait_with_output().unwrap();
+    feeder.join().unwrap();
+    output
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Text with plenty of repeats
+fn sample() -> Vec<u8> {
+    b"It was the best of times, it was the worst of times, it was the age of wisdom. ".repeat(200)
+}
+
+#[test]
+fn round_trips_a_file() {
+    let dir = scratch_dir("round-trip");
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+
+    let output = lzss(&dir, &["compress", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(fs::metadata(dir.join("a.txt.lzss")).unwrap().len() < sample().len() as u64);
+
+    let output = lzss(&dir, &["-o", "b.txt", "decompress", "a.txt.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("b.txt")).unwrap(), sample());
+
+    // Without -o the name stored at compression comes back
+    fs::rename(dir.join("a.txt"), dir.join("original")).unwrap();
+    let output = lzss(&dir, &["decompress", "a.txt.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("a.txt")).unwrap(), sample());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn round_trips_stdin_to_stdout() {
+    let dir = scratch_dir("stdio");
+
+    let compressed = lzss(&dir, &["compress"], &sample());
+    assert!(compressed.status.success(), "{}", stderr(&compressed));
+    let decompressed = lzss(&dir, &["decompress", "-"], &compressed.stdout);
+    assert!(decompressed.status.success(), "{}", stderr(&decompressed));
+    assert_eq!(decompressed.stdout, sample());
+
+    // Diagnostics stay off stdout, and no files are left behind
+    assert!(stderr(&compressed).contains("Compressed"));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quiet_silences_diagnostics_only() {
+    let dir = scratch_dir("quiet");
+
+    let loud = lzss(&dir, &["compress"], &sample());
+    let quiet = lzss(&dir, &["-q", "compress"], &sample());
+    assert!(loud.status.success() && quiet.status.success());
+    assert!(stderr(&loud).contains("Reading file"));
+    assert_eq!(stderr(&quiet), "");
+    assert_eq!(quiet.stdout, loud.stdout);
+
+    // Errors still show
+    let missing = lzss(&dir, &["-q", "compress", "missing"], b"");
+    assert!(!missing.status.success());
+    assert!(stderr(&missing).contains("missing"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn auto_picks_the_direction_from_the_input() {
+    let dir = scratch_dir("auto");
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+
+    // No magic and no .lzss: compress
+    let output = lzss(&dir, &["auto", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(fs::read(dir.join("a.txt.lzss")).unwrap().starts_with(b"LZSS"));
+
+    // The magic: decompress, whatever the name
+    fs::rename(dir.join("a.txt.lzss"), dir.join("packed")).unwrap();
+    let output = lzss(&dir, &["-o", "unpacked", "auto", "packed"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("unpacked")).unwrap(), sample());
+
+    // A .lzss without the magic is in the classic format, with --raw
+    fs::write(dir.join("classic.lzss"), lzss::compress(&sample()).unwrap()).unwrap();
+    let output = lzss(&dir, &["auto", "classic.lzss"], b"");
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(!dir.join("classic").exists());
+    let output = lzss(&dir, &["--raw", "auto", "classic.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("classic")).unwrap(), sample());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn existing_output_needs_force() {
+    let dir = scratch_dir("force");
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+
+    let output = lzss(&dir, &["compress", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let first = fs::read(dir.join("a.txt.lzss")).unwrap();
+
+    // Changed input, so an overwrite would show
+    fs::write(dir.join("a.txt"), b"something else entirely").unwrap();
+    let output = lzss(&dir, &["compress", "a.txt"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("already exists; use --force"), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("a.txt.lzss")).unwrap(), first);
+
+    for force in ["--force", "-f"] {
+        let output = lzss(&dir, &[force, "compress", "a.txt"], b"");
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert_ne!(fs::read(dir.join("a.txt.lzss")).unwrap(), first);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn decompressing_restores_the_modification_time() {
+    let dir = scratch_dir("mtime");
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+    File::options().write(true).open(dir.join("a.txt")).unwrap().set_modified(mtime).unwrap();
+    let modified = |name: &str| fs::metadata(dir.join(name)).unwrap().modified().unwrap();
+
+    let output = lzss(&dir, &["compress", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = lzss(&dir, &["-o", "restored", "decompress", "a.txt.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let restored = modified("restored");
+    let skew = restored.duration_since(mtime).unwrap_or_else(|e| e.duration());
+    assert!(skew < Duration::from_secs(1), "restored {restored:?}, expected {mtime:?}");
+
+    // --no-time on either side leaves the output with the time it was written
+    for (compress, decompress) in [(&["--no-time"][..], &[][..]), (&[], &["--no-time"])] {
+        let _ = fs::remove_file(dir.join("a.txt.lzss"));
+        let _ = fs::remove_file(dir.join("restored"));
+        let output = lzss(&dir, &[compress, &["compress", "a.txt"]].concat(), b"");
+        assert!(output.status.success(), "{}", stderr(&output));
+        let output = lzss(&dir, &[decompress, &["-o", "restored", "decompress", "a.txt.lzss"]].concat(), b"");
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert!(modified("restored") > mtime + Duration::from_secs(3600));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn inputs_are_kept_unless_delete_is_given() {
+    let dir = scratch_dir("delete");
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+
+    // Kept by default, and with --keep overriding an earlier --delete
+    for args in [&["compress", "a.txt"][..], &["-f", "--delete", "-k", "compress", "a.txt"]] {
+        let output = lzss(&dir, args, b"");
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert!(dir.join("a.txt").exists() && dir.join("a.txt.lzss").exists());
+    }
+
+    let output = lzss(&dir, &["-f", "--delete", "compress", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!dir.join("a.txt").exists());
+
+    let output = lzss(&dir, &["--delete", "decompress", "a.txt.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!dir.join("a.txt.lzss").exists());
+    assert_eq!(fs::read(dir.join("a.txt")).unwrap(), sample());
+
+    // Nothing is removed when the operation fails
+    let mut truncated = lzss::compress(&sample()).unwrap();
+    truncated.pop();
+    fs::write(dir.join("b.lzss"), &truncated).unwrap();
+    let output = lzss(&dir, &["--delete", "decompress", "b.lzss"], b"");
+    assert!(!output.status.success());
+    assert!(dir.join("b.lzss").exists() && !dir.join("b").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_leave_no_output_behind() {
+    let dir = scratch_dir("cleanup");
+    // Long enough that part of the output is written before the end shows
+    // up missing
+    let mut truncated = lzss::compress(&sample().repeat(20)).unwrap();
+    truncated.pop();
+    fs::write(dir.join("a.lzss"), &truncated).unwrap();
+    fs::write(dir.join("b"), b"was here first").unwrap();
+
+    for args in [&["--raw", "decompress", "a.lzss"][..], &["--raw", "-f", "-o", "b", "decompress", "a.lzss"]] {
+        let output = lzss(&dir, args, b"");
+        assert_eq!(output.status.code(), Some(5), "{}", stderr(&output));
+    }
+    // Neither the output nor a temporary file is left, and what was at the
+    // output path is untouched
+    let mut names: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["a.lzss", "b"]);
+    assert_eq!(fs::read(dir.join("b")).unwrap(), b"was here first");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_closed_stdout_ends_quietly() {
+    let dir = scratch_dir("pipe");
+    let compressed = lzss::compress(&sample().repeat(100)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lzss"))
+        .args(["-q", "--raw", "decompress"])
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // As with `| head -c 0`, the reader goes away before any output
+    drop(child.stdout.take());
+    let _ = child.stdin.take().unwrap().write_all(&compressed);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn verify_reports_intact_and_corrupt_files() {
+    let dir = scratch_dir("verify");
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+    let output = lzss(&dir, &["compress", "a.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    fs::write(dir.join("classic.lzss"), lzss::compress(&sample()).unwrap()).unwrap();
+
+    for args in [&["verify", "a.txt.lzss"][..], &["--raw", "verify", "classic.lzss"]] {
+        let name = args[args.len() - 1];
+        let output = lzss(&dir, args, b"");
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{name}: OK\n"));
+    }
+
+    // The framed format has no checksum of the data, so damage is only
+    // certain to show where it breaks the structure: a cut, or bytes after
+    // the last frame.
+    let framed = fs::read(dir.join("a.txt.lzss")).unwrap();
+    fs::write(dir.join("cut.lzss"), &framed[..framed.len() - 1]).unwrap();
+    fs::write(dir.join("trailing.lzss"), [&framed[..], b"junk"].concat()).unwrap();
+    for (name, status) in [("cut.lzss", 5), ("trailing.lzss", 4)] {
+        let output = lzss(&dir, &["verify", name], b"");
+        assert_eq!(output.status.code(), Some(status), "{}", stderr(&output));
+        assert!(output.stdout.is_empty());
+        assert!(stderr(&output).contains(name));
+    }
+
+    // Nothing is written besides the files above
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 5);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stats_are_printed_only_when_asked_for() {
+    let dir = scratch_dir("stats");
+    let lines = ["Literals: ", "Matches: ", "Match length: average ", "Match lengths: "];
+
+    let output = lzss(&dir, &["--stats", "compress"], &sample());
+    assert!(output.status.success(), "{}", stderr(&output));
+    for line in lines {
+        assert!(stderr(&output).lines().any(|l| l.starts_with(line)), "no {line:?} in {}", stderr(&output));
+    }
+    // The data on stdout is the same either way
+    let plain = lzss(&dir, &["compress"], &sample());
+    assert_eq!(output.stdout, plain.stdout);
+    for line in lines {
+        assert!(!stderr(&plain).contains(line));
+    }
+
+    let output = lzss(&dir, &["--stats", "decompress"], &plain.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--stats only applies to compress"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_have_distinct_messages_and_statuses() {
+    let dir = scratch_dir("errors");
+    let expect = |args: &[&str], status: i32, message: &str| {
+        let output = lzss(&dir, args, b"");
+        assert_eq!(output.status.code(), Some(status), "{}", stderr(&output));
+        assert!(stderr(&output).contains(message), "no {message:?} in {}", stderr(&output));
+    };
+
+    expect(&["decompress", "missing.lzss"], 2, "missing.lzss: no such file or directory");
+
+    fs::write(dir.join("notes.txt"), "plain text, and not compressed at all").unwrap();
+    expect(&["-o", "out", "decompress", "notes.txt"], 4, "not an LZSS file");
+
+    let framed = lzss::Lzss::new().compress_framed(&sample()).unwrap();
+    fs::write(dir.join("cut.lzss"), &framed[..framed.len() / 2]).unwrap();
+    expect(&["-o", "out", "decompress", "cut.lzss"], 5, "compressed data is truncated");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::write(dir.join("locked"), sample()).unwrap();
+        fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't bind root, which may well be running this
+        if fs::read(dir.join("locked")).is_err() {
+            expect(&["compress", "locked"], 3, "locked: permission denied");
+        }
+        fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o644)).unwrap();
+    }
+
+    // The first failure's status stands for the run
+    expect(&["decompress", "cut.lzss", "missing.lzss", "notes.txt"], 5, "missing.lzss");
+    assert!(!dir.join("out").exists() && !dir.join("cut").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "checkpointed")]
+#[test]
+fn verify_locates_damage_in_checkpointed_files() {
+    let dir = scratch_dir("checkpointed");
+    // Several 64 KiB checkpoint intervals
+    let data: Vec<u8> = (0..400_000u32).map(|i| (i % 1000 / 7) as u8).collect();
+    fs::write(dir.join("a"), &data).unwrap();
+
+    let output = lzss(&dir, &["--checkpointed", "compress", "a"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = lzss(&dir, &["--checkpointed", "verify", "a.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = lzss(&dir, &["--checkpointed", "-o", "b", "decompress", "a.lzss"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("b")).unwrap(), data);
+
+    let mut damaged = fs::read(dir.join("a.lzss")).unwrap();
+    let offset = damaged.len() * 3 / 4;
+    damaged[offset] ^= 0x10;
+    fs::write(dir.join("damaged.lzss"), &damaged).unwrap();
+    let output = lzss(&dir, &["--checkpointed", "verify", "damaged.lzss"], b"");
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    let position: u64 = stderr(&output)
+        .split("near decompressed offset ")
+        .nth(1)
+        .and_then(|rest| rest.trim().parse().ok())
+        .unwrap_or_else(|| panic!("no offset in {}", stderr(&output)));
+    // The damage is in the interval after the last good checkpoint
+    assert!(position <= 300_000 && position + 2 * 65_536 > 300_000, "reported near {position}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn two_paths_are_an_input_and_its_output() {
+    let dir = scratch_dir("in-out");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(dir.join(name), sample()).unwrap();
+    }
+
+    let output = lzss(&dir, &["compress", "a.txt", "packed"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(dir.join("packed").exists() && !dir.join("a.txt.lzss").exists());
+    let output = lzss(&dir, &["decompress", "packed", "unpacked"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("unpacked")).unwrap(), sample());
+
+    // An existing output is refused rather than taken as a second input
+    let output = lzss(&dir, &["compress", "a.txt", "b.txt"], b"");
+    assert!(!output.status.success());
+    assert_eq!(fs::read(dir.join("b.txt")).unwrap(), sample());
+    let output = lzss(&dir, &["-o", "out", "compress", "a.txt", "b.txt"], b"");
+    assert!(!output.status.success());
+
+    // Three or more are a batch, each compressed next to itself
+    let output = lzss(&dir, &["compress", "a.txt", "b.txt", "c.txt"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    for name in ["a.txt.lzss", "b.txt.lzss", "c.txt.lzss"] {
+        assert!(dir.join(name).exists(), "no {name}");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn plain_files_are_not_taken_for_lzss() {
+    let dir = scratch_dir("plain");
+    // Text the classic decoder turns into something else without an error:
+    // 'O' is a flags byte announcing literals
+    let text = "Ok!\n";
+    fs::write(dir.join("notes.txt"), text).unwrap();
+    fs::write(dir.join("notes.lzss"), text).unwrap();
+    assert!(lzss::decompress(text.as_bytes()).is_ok());
+
+    for args in [&["decompress", "notes.txt", "out"][..], &["verify", "notes.txt"], &["auto", "notes.lzss"], &["decompress", "-"]] {
+        let output = lzss(&dir, args, text.as_bytes());
+        assert_eq!(output.status.code(), Some(4), "{args:?}: {}", stderr(&output));
+        assert!(stderr(&output).contains("not an LZSS file"), "{args:?}: {}", stderr(&output));
+        assert!(output.stdout.is_empty(), "{args:?}");
+    }
+    assert!(!dir.join("out").exists() && !dir.join("notes").exists());
+
+    // Asked for, it decodes as the classic format does
+    let output = lzss(&dir, &["--raw", "decompress", "notes.txt", "out"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(dir.join("out")).unwrap(), lzss::decompress(text.as_bytes()).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}